@@ -19,12 +19,25 @@
 // CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION
 // WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 
+use std::fs::File;
+use std::io::{Read, Write};
+
 use colored::*;
+use rustc_serialize::Decodable;
+use rustc_serialize::json;
 
 use ct_sdk::ct::s3::acl::CannedAcl;
 use ct_sdk::ct::s3::bucket::*;
 use ct_sdk::ct::s3::acl::*;
-use ct_sdk::ct::sdk::CTClient;
+use ct_sdk::ct::bucket::{BucketLoggingStatus, CTClientBucket, CorsConfiguration,
+                         LifecycleConfiguration, LifecycleExpiration, LifecycleStatus,
+                         RedirectAllRequestsTo, RoutingRule, VersioningConfiguration,
+                         VersioningStatus, WebsiteConfiguration};
+use ct_sdk::ct::access_log::parse_access_log;
+use ct_sdk::ct::decode::{decode, FromXml};
+use ct_sdk::ct::policy::{BucketPolicy, PolicyEffect, Principal, RefererConfiguration};
+use ct_sdk::ct::acl::{AccessControl, AclGrantee, AclOwner, AclPermission, CTClientAcl};
+use ct_sdk::CtError;
 
 use prettytable::Table;
 use prettytable::row::Row;
@@ -33,6 +46,8 @@ use prettytable::format::FormatBuilder;
 
 use clap::ArgMatches;
 
+use cli::client;
+
 /// 显示仓库列表(ls)
 ///
 /// * `-q`, `--quiet`: 只显示名字
@@ -48,7 +63,7 @@ pub fn list(args: &ArgMatches) {
 
     let quiet = args.is_present("quiet");
 
-    match CTClient::default_client().list_buckets() {
+    match client().list_buckets() {
         Ok(out) => match quiet {
             false => printstd!(out.buckets, name, creation_date),
             true => printlist!(out.buckets, name),
@@ -71,7 +86,7 @@ pub fn create(args: &ArgMatches) {
     let bucket = args.value_of("bucket_name").unwrap();
 
     print!("{}", bucket);
-    match CTClient::default_client().create_bucket(&CreateBucketRequest {
+    match client().create_bucket(&CreateBucketRequest {
         bucket: bucket.to_string(),
         ..Default::default()
     }) {
@@ -105,7 +120,7 @@ pub fn acl(args: &ArgMatches) {
     let write = args.is_present("write");
 
     print!("{}", bucket);
-    match CTClient::default_client().put_bucket_acl(&PutBucketAclRequest {
+    match client().put_bucket_acl(&PutBucketAclRequest {
         bucket: bucket.to_string(),
         acl: Some(match (read, write) {
             (true, true) => CannedAcl::PublicReadWrite,
@@ -154,7 +169,7 @@ pub fn delete(args: &ArgMatches) {
     let buckets = args.values_of("buckets").unwrap().collect::<Vec<_>>();
     let force = args.is_present("force");
 
-    let ct = CTClient::default_client();
+    let ct = client();
 
     let mut success = 0;
     let mut error = 0;
@@ -184,3 +199,623 @@ pub fn delete(args: &ArgMatches) {
         format!("{}", error).red()
     )
 }
+
+/// 读取配置文件：以 `<` 开头按 XML 解析，否则按 JSON 解析
+fn read_config<T: FromXml + Decodable>(path: &str) -> Result<T, String> {
+    let mut content = String::new();
+    try!(
+        File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut content))
+            .map_err(|err| format!("{}: {}", path, err))
+    );
+    match content.trim_left().starts_with("<") {
+        true => decode(&content, &[]).map_err(|err| format!("{}: {}", path, err)),
+        false => json::decode(&content).map_err(|err| format!("{}: {}", path, err)),
+    }
+}
+
+/// 打印操作结果
+fn print_result<T>(name: &str, result: Result<T, CtError>) {
+    match result {
+        Ok(_) => info!("{}\t{}", name, " ✓ ".green().bold()),
+        Err(err) => {
+            print_err!(err);
+            info!("{}\t{}", name, " ✗ ".red().bold());
+        }
+    }
+}
+
+struct LifecycleRow {
+    id: String,
+    prefix: String,
+    status: LifecycleStatus,
+    expiration: String,
+    multipart: String,
+}
+
+/// 管理生命周期规则
+///
+/// ```shell
+/// $ ct-cli bucket lifecycle <bucket_name> get
+/// $ ct-cli bucket lifecycle <bucket_name> set <file>
+/// $ ct-cli bucket lifecycle <bucket_name> rm
+/// ```
+///
+/// 规则文件为 XML（`LifecycleConfiguration`）或 JSON，例如 `logs/` 下的对象 30 天后过期：
+///
+/// ```json
+/// {"rules": [{"id": "logs", "prefix": "logs/", "status": "Enabled", "expiration": {"days": 30}}]}
+/// ```
+///
+/// 可选字段：`expiration.date`（过期日期），
+/// `abort_incomplete_multipart_upload.days_after_initiation`（清理未完成的分片上传）
+pub fn lifecycle(args: &ArgMatches) {
+    debug!("Bucket Lifecycle");
+    let bucket = args.value_of("bucket_name").unwrap();
+    let ct = client();
+
+    match args.subcommand() {
+        ("get", _) => match ct.get_bucket_lifecycle(bucket) {
+            Ok(out) => {
+                let rows = out.rules
+                    .into_iter()
+                    .map(|rule| LifecycleRow {
+                        id: rule.id,
+                        prefix: rule.prefix,
+                        status: rule.status,
+                        expiration: match rule.expiration {
+                            Some(LifecycleExpiration { days: Some(days), .. }) => {
+                                format!("{} days", days)
+                            }
+                            Some(LifecycleExpiration { date: Some(date), .. }) => date,
+                            _ => String::from("-"),
+                        },
+                        multipart: match rule.abort_incomplete_multipart_upload {
+                            Some(abort) => format!("{} days", abort.days_after_initiation),
+                            None => String::from("-"),
+                        },
+                    })
+                    .collect::<Vec<_>>();
+                printstd!(rows, id, prefix, status, expiration, multipart);
+            }
+            Err(err) => print_err!(err),
+        },
+        ("set", Some(args)) => {
+            let file = args.value_of("file").unwrap();
+            match read_config::<LifecycleConfiguration>(file) {
+                Ok(config) => print_result(bucket, ct.put_bucket_lifecycle(bucket, &config)),
+                Err(err) => error!("{}", err),
+            }
+        }
+        ("rm", _) => print_result(bucket, ct.delete_bucket_lifecycle(bucket)),
+        _ => {}
+    }
+}
+
+struct StatementRow {
+    sid: String,
+    effect: PolicyEffect,
+    principal: Principal,
+    actions: String,
+    resources: String,
+    conditions: String,
+}
+
+/// 管理仓库策略
+///
+/// ```shell
+/// $ ct-cli bucket policy <bucket_name> get [-j]
+/// $ ct-cli bucket policy <bucket_name> set <file>
+/// $ ct-cli bucket policy <bucket_name> rm
+/// ```
+///
+/// 策略文件为 JSON，例如只允许来自内网和 `example.com` 页面的读取：
+///
+/// ```json
+/// {
+///   "Version": "2012-10-17",
+///   "Statement": [{
+///     "Effect": "Allow",
+///     "Principal": "*",
+///     "Action": "s3:GetObject",
+///     "Resource": "arn:aws:s3:::<bucket_name>/*",
+///     "Condition": {
+///       "IpAddress": {"aws:SourceIp": "192.168.0.0/16"},
+///       "StringLike": {"aws:Referer": "http://*.example.com/*"}
+///     }
+///   }]
+/// }
+/// ```
+///
+/// 上传前会在本地检查版本、Action、Resource 和 IP 地址。
+///
+/// ### 选项
+/// * `-j`, `--json`: 输出 JSON 策略文件
+pub fn policy(args: &ArgMatches) {
+    debug!("Bucket Policy");
+    let bucket = args.value_of("bucket_name").unwrap();
+    let ct = client();
+
+    match args.subcommand() {
+        ("get", Some(args)) => match ct.get_bucket_policy(bucket) {
+            Ok(policy) => match args.is_present("json") {
+                true => info!("{}", policy),
+                false => {
+                    let rows = policy
+                        .statements
+                        .into_iter()
+                        .map(|statement| StatementRow {
+                            sid: statement.sid.unwrap_or(String::from("-")),
+                            effect: statement.effect,
+                            principal: statement.principal,
+                            actions: statement.actions.join(", "),
+                            resources: statement.resources.join(", "),
+                            conditions: statement
+                                .conditions
+                                .iter()
+                                .map(|condition| condition.to_string())
+                                .collect::<Vec<_>>()
+                                .join("; "),
+                        })
+                        .collect::<Vec<_>>();
+                    printstd!(rows, sid, effect, principal, actions, resources, conditions);
+                }
+            },
+            Err(err) => print_err!(err),
+        },
+        ("set", Some(args)) => {
+            let file = args.value_of("file").unwrap();
+            let mut content = String::new();
+            match File::open(file).and_then(|mut f| f.read_to_string(&mut content)) {
+                Ok(_) => match BucketPolicy::from_json(&content) {
+                    Ok(policy) => print_result(bucket, ct.put_bucket_policy(bucket, &policy)),
+                    Err(err) => print_err!(err),
+                },
+                Err(err) => error!("{}: {}", file, err),
+            }
+        }
+        ("rm", _) => print_result(bucket, ct.delete_bucket_policy(bucket)),
+        _ => {}
+    }
+}
+
+struct GrantRow {
+    kind: &'static str,
+    grantee: AclGrantee,
+    permission: AclPermission,
+}
+
+/// 打印授权表
+pub fn print_grants(acl: AccessControl) {
+    info!("OWNER: {} ({})", acl.owner.id, acl.owner.display_name);
+    let rows = acl.grants
+        .into_iter()
+        .map(|grant| GrantRow {
+            kind: grant.grantee.kind(),
+            grantee: grant.grantee,
+            permission: grant.permission,
+        })
+        .collect::<Vec<_>>();
+    printstd!(rows, kind, grantee, permission);
+}
+
+/// 读取 `grants` 参数并保留所有者的 `FULL_CONTROL`
+pub fn read_grants(args: &ArgMatches, owner: AclOwner) -> Result<AccessControl, CtError> {
+    let mut acl = AccessControl {
+        owner: owner,
+        grants: Vec::new(),
+    };
+    for grant in args.values_of("grants").unwrap() {
+        acl.grants.push(try!(grant.parse()));
+    }
+    if !args.is_present("no_owner") {
+        acl.keep_owner();
+    }
+    Ok(acl)
+}
+
+/// 管理仓库授权
+///
+/// ```shell
+/// $ ct-cli bucket grant <bucket_name> get
+/// $ ct-cli bucket grant <bucket_name> set [--noowner] <grants>...
+/// ```
+///
+/// 授权格式为 `<类型>:<被授权者>=<权限>`：
+/// * 类型：`id`（用户 ID）、`email`（邮箱）、`group`（`all` 所有人，`authenticated` 已登录用户，或组 URI）
+/// * 权限：`FULL_CONTROL`、`READ`、`WRITE`、`READ_ACP`、`WRITE_ACP`
+///
+/// ```shell
+/// $ ct-cli bucket grant photos set group:all=READ email:a@example.com=WRITE
+/// ```
+///
+/// ### 选项
+/// * `--noowner`: 不自动保留所有者的 `FULL_CONTROL`
+pub fn grant(args: &ArgMatches) {
+    debug!("Bucket Grants");
+    let bucket = args.value_of("bucket_name").unwrap();
+    let ct = client();
+
+    let current = ct.get_bucket_grants(bucket);
+    match (args.subcommand(), current) {
+        (("get", _), Ok(acl)) => print_grants(acl),
+        (("set", Some(args)), Ok(acl)) => match read_grants(args, acl.owner) {
+            Ok(acl) => print_result(bucket, ct.put_bucket_grants(bucket, &acl)),
+            Err(err) => print_err!(err),
+        },
+        (_, Err(err)) => print_err!(err),
+        _ => {}
+    }
+}
+
+struct CorsRow {
+    id: String,
+    origins: String,
+    methods: String,
+    headers: String,
+    expose: String,
+    max_age: String,
+}
+
+/// 管理跨域（CORS）规则
+///
+/// ```shell
+/// $ ct-cli bucket cors <bucket_name> get [-o <file>]
+/// $ ct-cli bucket cors <bucket_name> set <file>
+/// $ ct-cli bucket cors <bucket_name> rm
+/// ```
+///
+/// `get -o` 把规则导出到文件，`.json` 导出 JSON，其他导出 XML，可再用 `set` 导入。
+///
+/// 规则文件为 XML（`CORSConfiguration`）或 JSON，例如允许网页直传：
+///
+/// ```json
+/// {"rules": [{
+///   "allowed_origins": ["https://www.example.com"],
+///   "allowed_methods": ["PUT", "POST"],
+///   "allowed_headers": ["*"],
+///   "expose_headers": ["ETag"],
+///   "max_age_seconds": 3000
+/// }]}
+/// ```
+pub fn cors(args: &ArgMatches) {
+    debug!("Bucket CORS");
+    let bucket = args.value_of("bucket_name").unwrap();
+    let ct = client();
+
+    match args.subcommand() {
+        ("get", Some(args)) => match ct.get_bucket_cors(bucket) {
+            Ok(ref out) if args.is_present("output") => {
+                let path = args.value_of("output").unwrap();
+                let content = match path.ends_with(".json") {
+                    true => format!("{}\n", json::as_pretty_json(out)),
+                    false => out.to_xml(),
+                };
+                let result = File::create(path)
+                    .and_then(|mut file| file.write_all(content.as_bytes()));
+                print_result(path, result.map_err(CtError::from));
+            }
+            Ok(out) => {
+                let rows = out.rules
+                    .into_iter()
+                    .map(|rule| CorsRow {
+                        id: rule.id.unwrap_or(String::from("-")),
+                        origins: rule.allowed_origins.join(", "),
+                        methods: rule.allowed_methods.join(", "),
+                        headers: rule.allowed_headers.join(", "),
+                        expose: rule.expose_headers.join(", "),
+                        max_age: match rule.max_age_seconds {
+                            Some(seconds) => format!("{}s", seconds),
+                            None => String::from("-"),
+                        },
+                    })
+                    .collect::<Vec<_>>();
+                printstd!(rows, id, origins, methods, headers, expose, max_age);
+            }
+            Err(err) => print_err!(err),
+        },
+        ("set", Some(args)) => {
+            let file = args.value_of("file").unwrap();
+            match read_config::<CorsConfiguration>(file) {
+                Ok(config) => print_result(bucket, ct.put_bucket_cors(bucket, &config)),
+                Err(err) => error!("{}", err),
+            }
+        }
+        ("rm", _) => print_result(bucket, ct.delete_bucket_cors(bucket)),
+        _ => {}
+    }
+}
+
+struct RoutingRow {
+    prefix: String,
+    error_code: String,
+    redirect: String,
+}
+
+/// 管理静态网站托管
+///
+/// ```shell
+/// $ ct-cli bucket website <bucket_name> get
+/// $ ct-cli bucket website <bucket_name> set --index index.html [--error 404.html] [--rules <file>]
+/// $ ct-cli bucket website <bucket_name> set --redirect <host> [--protocol https]
+/// $ ct-cli bucket website <bucket_name> rm
+/// ```
+///
+/// ### 选项
+/// * `--index`: 目录的默认文档
+/// * `--error`: 4XX 错误时返回的对象
+/// * `--rules`: 重定向规则文件，XML（`RoutingRules`）或 JSON，例如：
+///
+/// ```json
+/// [{"condition": {"key_prefix_equals": "docs/"}, "redirect": {"replace_key_prefix_with": "documents/"}}]
+/// ```
+/// * `--redirect`: 将所有请求重定向到该域名，不能与其他选项同时使用
+/// * `--protocol`: 重定向使用的协议（http, https）
+pub fn website(args: &ArgMatches) {
+    debug!("Bucket Website");
+    let bucket = args.value_of("bucket_name").unwrap();
+    let ct = client();
+
+    match args.subcommand() {
+        ("get", _) => match ct.get_bucket_website(bucket) {
+            Ok(website) => {
+                if let Some(redirect) = website.redirect_all_requests_to {
+                    info!(
+                        "REDIRECT: {}://{}",
+                        redirect.protocol.unwrap_or(String::from("http")),
+                        redirect.host_name
+                    );
+                }
+                if let Some(index) = website.index_document {
+                    info!("INDEX: {}", index);
+                }
+                if let Some(error) = website.error_document {
+                    info!("ERROR: {}", error);
+                }
+                let rows = website
+                    .routing_rules
+                    .into_iter()
+                    .map(|rule| {
+                        let condition = rule.condition.unwrap_or_default();
+                        let redirect = rule.redirect;
+                        RoutingRow {
+                            prefix: condition.key_prefix_equals.unwrap_or(String::from("*")),
+                            error_code: condition
+                                .http_error_code_returned_equals
+                                .map(|code| code.to_string())
+                                .unwrap_or(String::from("-")),
+                            redirect: format!(
+                                "{}{}{} ({})",
+                                redirect
+                                    .host_name
+                                    .map(|host| format!("//{}/", host))
+                                    .unwrap_or_default(),
+                                redirect.replace_key_prefix_with.unwrap_or_default(),
+                                redirect.replace_key_with.unwrap_or_default(),
+                                redirect.http_redirect_code.unwrap_or(301)
+                            ),
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                if !rows.is_empty() {
+                    printstd!(rows, prefix, error_code, redirect);
+                }
+            }
+            Err(err) => print_err!(err),
+        },
+        ("set", Some(args)) => {
+            let website = match args.value_of("redirect") {
+                Some(host) => WebsiteConfiguration {
+                    redirect_all_requests_to: Some(RedirectAllRequestsTo {
+                        host_name: host.to_string(),
+                        protocol: args.value_of("protocol").map(|p| p.to_string()),
+                    }),
+                    ..Default::default()
+                },
+                None => {
+                    let mut website = WebsiteConfiguration::new(
+                        args.value_of("index").unwrap_or("index.html"),
+                        args.value_of("error"),
+                    );
+                    if let Some(file) = args.value_of("rules") {
+                        match read_config::<Vec<RoutingRule>>(file) {
+                            Ok(rules) => website.routing_rules = rules,
+                            Err(err) => {
+                                error!("{}", err);
+                                return;
+                            }
+                        }
+                    }
+                    website
+                }
+            };
+            print_result(bucket, ct.put_bucket_website(bucket, &website));
+        }
+        ("rm", _) => print_result(bucket, ct.delete_bucket_website(bucket)),
+        _ => {}
+    }
+}
+
+struct AccessRow {
+    time: String,
+    remote_ip: String,
+    requester: String,
+    operation: String,
+    key: String,
+    status: String,
+    bytes_sent: String,
+}
+
+fn or_dash<T: ToString>(value: Option<T>) -> String {
+    value.map(|value| value.to_string()).unwrap_or(String::from("-"))
+}
+
+/// 管理访问日志
+///
+/// ```shell
+/// $ ct-cli bucket logging <bucket_name> get
+/// $ ct-cli bucket logging <bucket_name> set <target_bucket> [-p <prefix>]
+/// $ ct-cli bucket logging <bucket_name> rm
+/// $ ct-cli bucket logging <bucket_name> parse <files>...
+/// ```
+///
+/// 日志写入 `target_bucket` 中以 `prefix` 开头的对象，`target_bucket` 需授予日志组
+/// `WRITE` 和 `READ_ACP` 权限。`parse` 解析下载的日志文件，列出该仓库的请求，
+/// 无法解析的行会报告行号并跳过。
+///
+/// ### 选项
+/// * `-p`, `--prefix`: 日志对象前缀，默认为 `<bucket_name>/`
+pub fn logging(args: &ArgMatches) {
+    debug!("Bucket Logging");
+    let bucket = args.value_of("bucket_name").unwrap();
+    let ct = client();
+
+    match args.subcommand() {
+        ("get", _) => match ct.get_bucket_logging(bucket) {
+            Ok(BucketLoggingStatus { logging_enabled: Some(logging) }) => {
+                info!("{}{}", logging.target_bucket, logging.target_prefix)
+            }
+            Ok(_) => info!("disabled"),
+            Err(err) => print_err!(err),
+        },
+        ("set", Some(args)) => {
+            let target = args.value_of("target_bucket").unwrap();
+            let prefix = match args.value_of("prefix") {
+                Some(prefix) => prefix.to_string(),
+                None => format!("{}/", bucket),
+            };
+            let logging = BucketLoggingStatus::enabled(target, &prefix);
+            print_result(bucket, ct.put_bucket_logging(bucket, &logging));
+        }
+        ("rm", _) => print_result(
+            bucket,
+            ct.put_bucket_logging(bucket, &BucketLoggingStatus::default()),
+        ),
+        ("parse", Some(args)) => {
+            let mut rows = Vec::new();
+            for file in args.values_of("files").unwrap() {
+                let mut content = String::new();
+                let read = File::open(file).and_then(|mut f| f.read_to_string(&mut content));
+                if let Err(err) = read {
+                    error!("{}: {}", file, err);
+                    continue;
+                }
+                let access_log = parse_access_log(&content);
+                for &(line, ref err) in &access_log.invalid_lines {
+                    error!("{}:{}: {}", file, line, err);
+                }
+                rows.extend(
+                    access_log
+                        .records
+                        .into_iter()
+                        .filter(|record| record.bucket == bucket)
+                        .map(|record| AccessRow {
+                            time: record.time.to_rfc3339(),
+                            remote_ip: record.remote_ip,
+                            requester: or_dash(record.requester),
+                            operation: record.operation,
+                            key: or_dash(record.key),
+                            status: or_dash(record.http_status),
+                            bytes_sent: or_dash(record.bytes_sent),
+                        }),
+                );
+            }
+            printstd!(rows, time, remote_ip, requester, operation, key, status, bytes_sent);
+        }
+        _ => {}
+    }
+}
+
+/// 管理防盗链（Referer 白名单、黑名单）
+///
+/// ```shell
+/// $ ct-cli bucket referer <bucket_name> get
+/// $ ct-cli bucket referer <bucket_name> set [-a <referers>...] [-d <referers>...] [--noempty]
+/// $ ct-cli bucket referer <bucket_name> rm
+/// ```
+///
+/// 配置保存在仓库策略中，不影响策略的其他条目。Referer 可使用通配符 `*`，例如
+/// `http://*.example.com/*`。
+///
+/// ### 选项
+/// * `-a`, `--allow`: 白名单，只允许这些页面读取对象
+/// * `-d`, `--deny`: 黑名单，禁止这些页面读取对象
+/// * `--noempty`: 拒绝没有 Referer 的请求，需要同时设置白名单（所有者的请求也会被拒绝）
+pub fn referer(args: &ArgMatches) {
+    debug!("Bucket Referer");
+    let bucket = args.value_of("bucket_name").unwrap();
+    let ct = client();
+
+    let values = |args: &ArgMatches, name: &str| match args.values_of(name) {
+        Some(values) => values.map(|value| value.to_string()).collect(),
+        None => Vec::new(),
+    };
+
+    match args.subcommand() {
+        ("get", _) => match ct.get_bucket_referer(bucket) {
+            Ok(referer) => printstc!(
+                RefererRow {
+                    allowed: referer.allowed.join(", "),
+                    denied: referer.denied.join(", "),
+                    allow_empty: referer.allow_empty,
+                },
+                allowed,
+                denied,
+                allow_empty
+            ),
+            Err(err) => print_err!(err),
+        },
+        ("set", Some(args)) => {
+            let referer = RefererConfiguration {
+                allowed: values(args, "allowed"),
+                denied: values(args, "denied"),
+                allow_empty: !args.is_present("no_empty"),
+            };
+            print_result(bucket, ct.put_bucket_referer(bucket, &referer));
+        }
+        ("rm", _) => print_result(
+            bucket,
+            ct.put_bucket_referer(bucket, &RefererConfiguration::default()),
+        ),
+        _ => {}
+    }
+}
+
+struct RefererRow {
+    allowed: String,
+    denied: String,
+    allow_empty: bool,
+}
+
+/// 管理多版本
+///
+/// ```shell
+/// $ ct-cli bucket versioning <bucket_name> get
+/// $ ct-cli bucket versioning <bucket_name> enable
+/// $ ct-cli bucket versioning <bucket_name> suspend
+/// ```
+///
+/// 开启后无法关闭，只能暂停；暂停后已有的版本仍然保留，可通过
+/// [`object restore`](../object/fn.restore.html) 恢复。
+pub fn versioning(args: &ArgMatches) {
+    debug!("Bucket Versioning");
+    let bucket = args.value_of("bucket_name").unwrap();
+    let ct = client();
+
+    match args.subcommand() {
+        ("get", _) => match ct.get_bucket_versioning(bucket) {
+            Ok(VersioningConfiguration { status: Some(status) }) => info!("{}", status),
+            Ok(_) => info!("disabled"),
+            Err(err) => print_err!(err),
+        },
+        ("enable", _) => print_result(
+            bucket,
+            ct.put_bucket_versioning(bucket, VersioningStatus::Enabled),
+        ),
+        ("suspend", _) => print_result(
+            bucket,
+            ct.put_bucket_versioning(bucket, VersioningStatus::Suspended),
+        ),
+        _ => {}
+    }
+}
@@ -19,8 +19,14 @@
 // CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION
 // WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 
-use ct_sdk::ct::sdk::CTClient;
+use std::fs::File;
+use std::io::Read;
+use std::time::Duration;
+
+use rustc_serialize::json::Json;
+
 use ct_sdk::ct::iam::*;
+use ct_sdk::ct::AwsCredentialsProvider;
 
 use prettytable::Table;
 use prettytable::row::Row;
@@ -31,6 +37,8 @@ use colored::*;
 
 use clap::ArgMatches;
 
+use cli::{client, profile};
+
 /// 显示账户列表(ls)
 ///
 /// ```shell
@@ -39,6 +47,8 @@ use clap::ArgMatches;
 /// ### 选项
 /// * `-q`, `--quiet`: 只显示名字
 /// * `-a`, `--all`: 默认只显示非主要账户
+/// * `-n`, `--pagesize`: 每次请求的数量，自动翻页直到列出全部
+/// * `-u`, `--user`: 子用户的 AK/SK
 ///
 /// ### 截图
 /// ![iam-ls.png] (https://i.loli.net/2017/11/21/5a13ad0126805.png)
@@ -47,40 +57,49 @@ pub fn list(args: &ArgMatches) {
     let quiet = args.is_present("quiet");
     let all = args.is_present("all");
 
-    let ct = CTClient::default_client();
+    let page_size = args.value_of("page_size").map(|size| size.to_string());
 
-    match ct.list_access_key(&ListAccessKeyRequest {
+    let ct = client();
+
+    let mut out = Vec::new();
+    for key in ct.list_access_key_iter(&ListAccessKeyRequest {
+        max_items: page_size,
+        user_name: args.value_of("user_name").map(|name| name.to_string()),
         ..Default::default()
     }) {
-        Ok(out) => {
-            let out = match all {
-                false => out.access_key_metadata
-                    .member
-                    .iter()
-                    .filter(|elm| !elm.is_primary)
-                    .collect::<Vec<_>>(),
-                true => out.access_key_metadata.member.iter().collect::<Vec<_>>(),
-            };
-            match quiet {
-                false => printstd!(out, access_key_id, user_name, status, is_primary),
-                true => printlist!(out, access_key_id),
-            }
-        }
-        Err(err) => info!("{:?}", err),
+        match key {
+            Ok(key) => if all || !key.is_primary {
+                out.push(key)
+            },
+            Err(err) => {
+                print_err!(err);
+                break;
+            }
+        }
+    }
+    match quiet {
+        false => printstd!(out, access_key_id, user_name, status, is_primary),
+        true => printlist!(out, access_key_id),
     }
 }
 
 /// 创建一组 AK/SK
 ///
 /// ```shell
-/// $ ct-cli account new
+/// $ ct-cli account new [-p] [-u <用户名>]
 /// ```
+/// ### 选项
+/// * `-p`, `--isprimary`: 直接创建主秘钥
+/// * `-u`, `--user`: 为子用户创建
 ///
 /// ### 截图
 /// ![iam-new.png](https://i.loli.net/2017/11/21/5a13ad020a705.png)
 pub fn create(args: &ArgMatches) {
     debug!("Create Access Key");
-    match CTClient::default_client().create_access_key() {
+    match client().create_access_key_for(&CreateAccessKeyRequest {
+        is_primary: args.is_present("is_primary"),
+        user_name: args.value_of("user_name").map(|name| name.to_string()),
+    }) {
         Ok(out) => printstc!(
             out,
             access_key_id,
@@ -103,6 +122,7 @@ pub fn create(args: &ArgMatches) {
 /// ```
 /// ### 选项
 /// * `-f`, `--force` 默认只删除非主 KEY　（未实现）
+/// * `-u`, `--user` 子用户的 AK/SK
 ///
 /// ### 截图
 /// ![iam-rm.png](https://i.loli.net/2017/11/21/5a13ad0203dc0.png)
@@ -113,7 +133,7 @@ pub fn delete(args: &ArgMatches) {
     let access_keys = args.values_of("access_keys").unwrap().collect::<Vec<_>>();
     let force = args.is_present("force");
 
-    let ct = CTClient::default_client();
+    let ct = client();
 
     let mut success = 0;
     let mut error = 0;
@@ -121,6 +141,7 @@ pub fn delete(args: &ArgMatches) {
     access_keys.iter().for_each(|access_key| {
         match ct.delete_access_key(&DeleteAccessKeyRequest {
             access_key_id: access_key.to_string(),
+            user_name: args.value_of("user_name").map(|name| name.to_string()),
         }) {
             Ok(out) => {
                 debug!("{:#?}", out);
@@ -151,6 +172,7 @@ pub fn delete(args: &ArgMatches) {
 /// ### 选项
 /// * `-s`, `--status` 激活状态
 /// * `-p`, `--is_primary` 主秘钥/普通秘钥
+/// * `-u`, `--user` 子用户的 AK/SK
 ///
 /// ### 截图
 /// ![iam-update.png](https://i.loli.net/2017/11/21/5a13adf92bfb2.png)
@@ -161,13 +183,14 @@ pub fn update(args: &ArgMatches) {
     let status = args.is_present("status");
     let is_primary = args.is_present("is_primary");
 
-    match CTClient::default_client().update_access_key(&UpdateAccessKeyRequest {
+    match client().update_access_key(&UpdateAccessKeyRequest {
         access_key_id: access_key_id.clone(),
         status: match status {
             true => Some(Status::Active),
             false => Some(Status::Inactive),
         },
         is_primary: Some(is_primary),
+        user_name: args.value_of("user_name").map(|name| name.to_string()),
     }) {
         Ok(out) => {
             debug!("{:#?}", out);
@@ -179,3 +202,296 @@ pub fn update(args: &ArgMatches) {
         }
     }
 }
+
+/// 轮换 AK/SK：新建 AK/SK，验证可用后写入凭证文件，再禁用旧 AK
+///
+/// ```shell
+/// $ ct-cli account rotate [ak] [-u <用户名>] [-d <秒>] [--nosave] [--profile <配置名>]
+/// ```
+/// ### 选项
+/// * `ak` 被替换的 AK，默认为当前使用的 AK
+/// * `-u`, `--user` 轮换子用户的 AK，此时不写入凭证文件
+/// * `-d`, `--delete` 等待指定秒数后删除旧 AK（默认只禁用）
+/// * `--nosave` 不写入凭证文件（`~/.ct/credentials`）
+pub fn rotate(args: &ArgMatches) {
+    debug!("Rotate Access Key");
+
+    let ct = client();
+
+    let access_key_id = match args.value_of("access_key_id") {
+        Some(access_key_id) => access_key_id.to_string(),
+        None => match ct.credentials_provider().credentials() {
+            Ok(credentials) => credentials.aws_access_key_id().to_string(),
+            Err(err) => {
+                error!("{}", err.message);
+                return;
+            }
+        },
+    };
+
+    let delete_after = match args.is_present("delete") {
+        true => {
+            let seconds = value_t!(args, "delete", u64).unwrap_or_else(|e| e.exit());
+            Some(Duration::from_secs(seconds))
+        }
+        false => None,
+    };
+
+    let user_name = args.value_of("user_name").map(|name| name.to_string());
+
+    match ct.rotate_access_key(&RotateAccessKeyRequest {
+        access_key_id: access_key_id.clone(),
+        profile: match args.is_present("no_save") || user_name.is_some() {
+            true => None,
+            false => Some(profile()),
+        },
+        user_name: user_name,
+        delete_after: delete_after,
+    }) {
+        Ok(out) => {
+            debug!("{:#?}", out);
+            let key = &out.access_key;
+            printstc!(key, access_key_id, secret_access_key, status, is_primary);
+            if let Some(ref path) = out.saved_to {
+                info!("{}\t{:?}", " ✓ ".green().bold(), path);
+            }
+            match out.deleted {
+                true => info!("{}\t{} deleted", " ✓ ".green().bold(), access_key_id),
+                false => info!("{}\t{} inactive", " ✓ ".green().bold(), access_key_id),
+            }
+        }
+        Err(err) => {
+            print_err!(err);
+            info!("{}\t{}", " ✗ ".red().bold(), access_key_id);
+        }
+    }
+}
+
+/// 显示子用户列表
+///
+/// ```shell
+/// $ ct-cli account user ls [-q]
+/// ```
+/// ### 选项
+/// * `-q`, `--quiet`: 只显示用户名
+pub fn user_list(args: &ArgMatches) {
+    debug!("List Users");
+    let quiet = args.is_present("quiet");
+
+    let ct = client();
+
+    let mut users = Vec::new();
+    for user in ct.list_users_iter(&ListUsersRequest::default()) {
+        match user {
+            Ok(user) => users.push(user),
+            Err(err) => {
+                print_err!(err);
+                break;
+            }
+        }
+    }
+    match quiet {
+        false => printstd!(users, user_name, user_id, create_date),
+        true => printlist!(users, user_name),
+    }
+}
+
+/// 创建子用户
+///
+/// ```shell
+/// $ ct-cli account user new <用户名>...
+/// ```
+pub fn user_create(args: &ArgMatches) {
+    debug!("Create Users");
+    let ct = client();
+
+    args.values_of("user_names").unwrap().for_each(|user_name| {
+        match ct.create_user(&CreateUserRequest {
+            user_name: user_name.to_string(),
+        }) {
+            Ok(out) => {
+                debug!("{:#?}", out);
+                info!("{}\t{}\t{}", " ✓ ".green().bold(), user_name, out.user.arn);
+            }
+            Err(err) => {
+                print_err!(err);
+                info!("{}\t{}", " ✗ ".red().bold(), user_name);
+            }
+        }
+    });
+}
+
+/// 删除子用户（需先删除其 AK/SK 并解除策略）
+///
+/// ```shell
+/// $ ct-cli account user rm <用户名>...
+/// ```
+pub fn user_delete(args: &ArgMatches) {
+    debug!("Delete Users");
+    let ct = client();
+
+    args.values_of("user_names").unwrap().for_each(|user_name| {
+        match ct.delete_user(&DeleteUserRequest {
+            user_name: user_name.to_string(),
+        }) {
+            Ok(out) => {
+                debug!("{:#?}", out);
+                info!("{}\t{}", " ✓ ".green().bold(), user_name);
+            }
+            Err(err) => {
+                print_err!(err);
+                info!("{}\t{}", " ✗ ".red().bold(), user_name);
+            }
+        }
+    });
+}
+
+/// 显示子用户的策略
+///
+/// ```shell
+/// $ ct-cli account user policies <用户名>
+/// ```
+pub fn user_policies(args: &ArgMatches) {
+    debug!("List User Policies");
+    let ct = client();
+
+    let mut policies = Vec::new();
+    for policy in ct.list_attached_user_policies_iter(&ListAttachedUserPoliciesRequest {
+        user_name: args.value_of("user_name").unwrap().to_string(),
+        ..Default::default()
+    }) {
+        match policy {
+            Ok(policy) => policies.push(policy),
+            Err(err) => {
+                print_err!(err);
+                break;
+            }
+        }
+    }
+    printstd!(policies, policy_name, policy_arn);
+}
+
+/// 为子用户添加/解除策略
+///
+/// ```shell
+/// $ ct-cli account user attach <用户名> <策略 ARN>...
+/// $ ct-cli account user detach <用户名> <策略 ARN>...
+/// ```
+pub fn user_policy(args: &ArgMatches, attach: bool) {
+    debug!("Attach/Detach User Policy");
+    let ct = client();
+    let user_name = args.value_of("user_name").unwrap();
+
+    args.values_of("policy_arns").unwrap().for_each(|policy_arn| {
+        let request = UserPolicyRequest {
+            user_name: user_name.to_string(),
+            policy_arn: policy_arn.to_string(),
+        };
+        let result = match attach {
+            true => ct.attach_user_policy(&request),
+            false => ct.detach_user_policy(&request),
+        };
+        match result {
+            Ok(out) => {
+                debug!("{:#?}", out);
+                info!("{}\t{}", " ✓ ".green().bold(), policy_arn);
+            }
+            Err(err) => {
+                print_err!(err);
+                info!("{}\t{}", " ✗ ".red().bold(), policy_arn);
+            }
+        }
+    });
+}
+
+/// 显示策略列表
+///
+/// ```shell
+/// $ ct-cli account policy ls [-q]
+/// ```
+/// ### 选项
+/// * `-q`, `--quiet`: 只显示 ARN
+pub fn policy_list(args: &ArgMatches) {
+    debug!("List Policies");
+    let quiet = args.is_present("quiet");
+
+    let ct = client();
+
+    let mut policies = Vec::new();
+    for policy in ct.list_policies_iter(&ListPoliciesRequest::default()) {
+        match policy {
+            Ok(policy) => policies.push(policy),
+            Err(err) => {
+                print_err!(err);
+                break;
+            }
+        }
+    }
+    match quiet {
+        false => printstd!(policies, policy_name, arn, attachment_count, description),
+        true => printlist!(policies, arn),
+    }
+}
+
+/// 由 JSON 策略文档创建策略
+///
+/// ```shell
+/// $ ct-cli account policy new <策略名> <文件> [-d <描述>]
+/// ```
+/// ### 选项
+/// * `-d`, `--description`: 描述
+pub fn policy_create(args: &ArgMatches) {
+    debug!("Create Policy");
+    let policy_name = args.value_of("policy_name").unwrap();
+    let path = args.value_of("document").unwrap();
+
+    let mut document = String::new();
+    if let Err(err) = File::open(path).and_then(|mut file| file.read_to_string(&mut document)) {
+        error!("{}: {}", path, err);
+        return;
+    }
+    if let Err(err) = Json::from_str(&document) {
+        error!("{}: {}", path, err);
+        return;
+    }
+
+    match client().create_policy(&CreatePolicyRequest {
+        policy_name: policy_name.to_string(),
+        policy_document: document,
+        description: args.value_of("description").map(|d| d.to_string()),
+    }) {
+        Ok(out) => {
+            debug!("{:#?}", out);
+            info!("{}\t{}", " ✓ ".green().bold(), out.policy.arn);
+        }
+        Err(err) => {
+            print_err!(err);
+            info!("{}\t{}", " ✗ ".red().bold(), policy_name);
+        }
+    }
+}
+
+/// 删除策略（需先从子用户解除）
+///
+/// ```shell
+/// $ ct-cli account policy rm <策略 ARN>...
+/// ```
+pub fn policy_delete(args: &ArgMatches) {
+    debug!("Delete Policies");
+    let ct = client();
+
+    args.values_of("policy_arns").unwrap().for_each(|policy_arn| {
+        match ct.delete_policy(&DeletePolicyRequest {
+            policy_arn: policy_arn.to_string(),
+        }) {
+            Ok(out) => {
+                debug!("{:#?}", out);
+                info!("{}\t{}", " ✓ ".green().bold(), policy_arn);
+            }
+            Err(err) => {
+                print_err!(err);
+                info!("{}\t{}", " ✗ ".red().bold(), policy_arn);
+            }
+        }
+    });
+}
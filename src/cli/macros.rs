@@ -85,3 +85,12 @@ macro_rules! print_aws_err {
         error!("{}", $error.message);
     });
 }
+
+/// 打印一个 `CtError`
+#[macro_export]
+macro_rules! print_err {
+    ($error:expr) => ({
+        debug!("{:#?}", $error);
+        error!("{}", $error);
+    });
+}
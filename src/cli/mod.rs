@@ -26,7 +26,47 @@ mod macros;
 /// High-level OOS object operations commands
 /// Like http://docs.aws.amazon.com/cli/latest/reference/s3/index.html
 pub mod object;
+pub mod post;
 pub mod bucket;
 pub mod iam;
+pub mod stats;
 
 pub use ct_sdk::ct::sdk::CTClient;
+
+use std::sync::RwLock;
+use ct_sdk::ct::credentials::CtProfileProvider;
+
+/// 由全局参数（`--ak`、`--sk`、`--profile`、`--proxy`、`--noproxy`、`--ca-bundle`）创建
+struct Options {
+    client: CTClient,
+    profile: CtProfileProvider,
+}
+
+lazy_static! {
+    static ref OPTIONS: RwLock<Option<Options>> = RwLock::new(None);
+}
+
+/// 保存全局客户端，在 `main` 解析参数后、执行子命令前调用
+pub fn init(client: CTClient, profile: CtProfileProvider) {
+    let mut options = OPTIONS.write().unwrap();
+    *options = Some(Options {
+        client: client,
+        profile: profile,
+    });
+}
+
+/// 全局客户端，各个克隆共享连接池；未调用 `init` 时为 `CTClient::default_client()`
+pub fn client() -> CTClient {
+    match *OPTIONS.read().unwrap() {
+        Some(ref options) => options.client.clone(),
+        None => CTClient::default_client(),
+    }
+}
+
+/// `--profile` 对应的凭证配置
+pub fn profile() -> CtProfileProvider {
+    match *OPTIONS.read().unwrap() {
+        Some(ref options) => options.profile.clone(),
+        None => CtProfileProvider::new(),
+    }
+}
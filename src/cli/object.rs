@@ -26,12 +26,21 @@ use std::io::Write;
 
 use colored::*;
 
+use std::time::Duration as StdDuration;
+
+use chrono::Duration;
+
 use rustc_serialize::base64::{ToBase64, STANDARD};
 
 use ct_sdk::ct::sdk::CTClient;
 use ct_sdk::ct::sdk::md5;
 use ct_sdk::ct::object::*;
-use ct_sdk::ct::errors::s3::S3Error;
+use ct_sdk::ct::error::CtError;
+use ct_sdk::ct::acl::CTClientAcl;
+
+use cli::client;
+use cli::bucket::{print_grants, read_grants};
+use cli::post::{serve as serve_forms, ServeOptions};
 
 use prettytable::Table;
 use prettytable::row::Row;
@@ -43,12 +52,13 @@ use clap::ArgMatches;
 /// 列出对象
 ///
 /// ```shell
-/// ct-cli object <bucket> ls [-p] [-q]
+/// ct-cli object <bucket> ls [-p] [-q] [--versions]
 /// ```
 ///
 /// ### 选项
 /// * `-p`, `--prefix`: 过滤前缀
 /// * `-q`, `--quiet`: 只显示名字
+/// * `--versions`: 列出所有版本和删除标记，`*` 为最新版本
 ///
 /// ### 截图
 /// ![object--prefix-delete.png](https://i.loli.net/2017/11/21/5a13b0bfa6f07.png)
@@ -62,7 +72,11 @@ pub fn list(bucket: &str, args: &ArgMatches) {
     //let encoding_type = args.value_of("encoding_type").unwrap();
     let quiet = args.is_present("quiet");
 
-    match CTClient::default_client().list_objects(&ListObjectsRequest {
+    if args.is_present("versions") {
+        return list_versions(bucket, prefix, quiet);
+    }
+
+    match client().list_objects(&ListObjectsRequest {
         bucket: bucket.to_string(),
         //version: version.to_string(),
         prefix: match prefix {
@@ -83,6 +97,72 @@ pub fn list(bucket: &str, args: &ArgMatches) {
     }
 }
 
+/// 列出对象的所有版本，自动翻页
+fn list_versions(bucket: &str, prefix: Option<&str>, quiet: bool) {
+    let ct = client();
+    let mut request = ObjectVersionsRequest {
+        bucket: bucket.to_string(),
+        prefix: prefix.map(|prefix| prefix.to_string()),
+        ..Default::default()
+    };
+
+    let mut rows = Vec::new();
+    loop {
+        let out = match ct.list_object_versions(&request) {
+            Ok(out) => out,
+            Err(err) => {
+                print_err!(err);
+                return;
+            }
+        };
+        rows.extend(out.versions.into_iter().map(|version| VersionRow {
+            key: version.key,
+            version_id: version.version_id,
+            latest: latest_mark(version.is_latest),
+            last_modified: version.last_modified,
+            size: version.size.to_string(),
+        }));
+        rows.extend(out.delete_markers.into_iter().map(|marker| VersionRow {
+            key: marker.key,
+            version_id: marker.version_id,
+            latest: latest_mark(marker.is_latest),
+            last_modified: marker.last_modified,
+            size: String::from("DeleteMarker"),
+        }));
+        if !out.is_truncated {
+            break;
+        }
+        request.key_marker = out.next_key_marker;
+        request.version_id_marker = out.next_version_id_marker;
+    }
+    rows.sort_by(|a, b| {
+        a.key
+            .cmp(&b.key)
+            .then_with(|| b.last_modified.cmp(&a.last_modified))
+    });
+
+    match quiet {
+        false => printstd!(rows, key, version_id, latest, last_modified, size),
+        true => rows.iter()
+            .for_each(|row| info!("{}\t{}", row.key, row.version_id)),
+    }
+}
+
+fn latest_mark(is_latest: bool) -> &'static str {
+    match is_latest {
+        true => "*",
+        false => "",
+    }
+}
+
+struct VersionRow {
+    key: String,
+    version_id: String,
+    latest: &'static str,
+    last_modified: String,
+    size: String,
+}
+
 /// 新建对象（暂未提供接口）
 ///
 /// ```shell
@@ -93,7 +173,7 @@ pub fn new(bucket: &str, args: &ArgMatches) {
     let key = args.value_of("key").unwrap();
     let body = args.value_of("body").unwrap();
 
-    match CTClient::default_client().put_object(
+    match client().put_object(
         &PutObjectRequest {
             bucket: bucket.to_string(),
             key: key.to_string(),
@@ -117,15 +197,17 @@ pub fn new(bucket: &str, args: &ArgMatches) {
 /// 读取对象，从 `clap` 中解析参数
 ///
 /// ```shell
-/// ct-cli object <bucket> get <key> [-e] [-k]
+/// ct-cli object <bucket> get <key> [-e] [-k] [--version-id <version_id>]
 /// ```
 ///
 /// ### 选项
 /// * `-e`, `--encryptmethod` 加密方式（aes-128-cfb, aes-128-cfb128, aes-256-cfb, aes-256-cfb128, rc4, rc4-md5...）
 /// * `-k`, `--password` 密钥
+/// * `--version-id`, `--versionid` 读取指定版本，版本号由 `ls --versions` 列出
 pub fn get_args(bucket: &str, args: &ArgMatches) {
     debug!("Get Object");
     let key = args.value_of("key").unwrap();
+    let version_id = args.value_of("version_id").map(|version| version.to_string());
 
     match match (args.value_of("PASSWORD"), args.value_of("ENCRYPT_METHOD")) {
         (Some(password), Some(method)) => get_securely(
@@ -133,20 +215,22 @@ pub fn get_args(bucket: &str, args: &ArgMatches) {
             key.to_string(),
             method.to_string(),
             password.to_string(),
+            version_id,
         ),
         (Some(password), None) => get_securely(
             bucket.to_string(),
             key.to_string(),
             "".to_string(),
             password.to_string(),
+            version_id,
         ),
-        _ => get(bucket.to_string(), key.to_string()),
+        _ => get(bucket.to_string(), key.to_string(), version_id),
     } {
         Ok(out) => info!(
             "+--[ START ]----+\n{}\n+--[  END  ]----+",
             String::from_utf8_lossy(out.get_body())
         ),
-        Err(err) => print_aws_err!(err),
+        Err(err) => print_err!(err),
     }
 }
 
@@ -167,7 +251,7 @@ pub fn down_args(bucket: &str, args: &ArgMatches) {
     let keys = args.values_of("keys").unwrap().collect::<Vec<_>>();
     let output = args.value_of("dir").unwrap_or("./");
 
-    let ct = CTClient::default_client();
+    let ct = client();
 
     keys.iter().for_each(|key| {
         match match (
@@ -180,9 +264,10 @@ pub fn down_args(bucket: &str, args: &ArgMatches) {
                 key.to_string(),
                 method.to_string(),
                 password.to_string(),
+                None,
             ),
             ((None, None), true) => unimplemented!(),
-            _ => get(bucket.to_string(), key.to_string()),
+            _ => get(bucket.to_string(), key.to_string(), None),
         } {
             Ok(out) => {
                 let mut file = match File::create(Path::new(format!("{}{}", output, key).as_str()))
@@ -206,7 +291,7 @@ pub fn down_args(bucket: &str, args: &ArgMatches) {
                     }
                 }
             }
-            Err(err) => print_aws_err!(err),
+            Err(err) => print_err!(err),
         }
     });
 }
@@ -214,19 +299,26 @@ pub fn down_args(bucket: &str, args: &ArgMatches) {
 /// 读取对象
 ///
 /// ```shell
-/// ct-cli object <bucket> get <key>
+/// ct-cli object <bucket> get <key> [--version-id <version_id>]
 /// ```
-pub fn get(bucket: String, key: String) -> Result<GetObjectOutput, S3Error> {
+pub fn get(
+    bucket: String,
+    key: String,
+    version_id: Option<String>,
+) -> Result<GetObjectOutput, CtError> {
     debug!("Get Object");
 
-    CTClient::default_client().get_object(
-        &GetObjectRequest {
-            bucket: bucket.to_string(),
-            key: key.to_string(),
-            ..Default::default()
-        },
-        None,
-    )
+    client()
+        .get_object(
+            &GetObjectRequest {
+                bucket: bucket.to_string(),
+                key: key.to_string(),
+                version_id,
+                ..Default::default()
+            },
+            None,
+        )
+        .map_err(CtError::from)
 }
 
 /// 读取加密对象
@@ -242,7 +334,8 @@ pub fn get_securely(
     key: String,
     method: String,
     password: String,
-) -> Result<GetObjectOutput, S3Error> {
+    version_id: Option<String>,
+) -> Result<GetObjectOutput, CtError> {
     debug!("Downland Object");
 
     let method = match method.parse() {
@@ -252,10 +345,11 @@ pub fn get_securely(
         }
     };
 
-    CTClient::default_securely_client(password, method).get_object_securely(
+    client().with_encryption(password, method).get_object_securely(
         &GetObjectRequest {
             bucket: bucket.to_string(),
             key: key.to_string(),
+            version_id,
             ..Default::default()
         },
         None,
@@ -287,6 +381,7 @@ pub fn put_args(bucket: &str, args: &ArgMatches) {
         Some(s) => s.to_string(),
         None => "".to_string(),
     };
+    let ct = client();
 
     keys.iter().for_each(|key| {
         if args.is_present("multipart") {
@@ -317,6 +412,7 @@ pub fn put_args(bucket: &str, args: &ArgMatches) {
                     reverse,
                 ),
                 ((None, None), true) => put_multithread(
+                    &ct,
                     bucket.to_string(),
                     Path::new(key),
                     prefix.to_string(),
@@ -398,7 +494,7 @@ pub fn put(bucket: String, path: &Path, prefix: String, storage_class: String, r
 
     request.content_md5 = Some(hash);
 
-    match CTClient::default_client().put_object(&request, None) {
+    match client().put_object(&request, None) {
         Ok(output) => {
             debug!("{:#?}", output);
             info!("{}\t{:?}", " ✓ ".green().bold(), path);
@@ -425,7 +521,7 @@ pub fn put(bucket: String, path: &Path, prefix: String, storage_class: String, r
 pub fn put_multipart(bucket: String,
                      path: &Path,
                      part_size: u64) {
-    let ct = CTClient::default_client();
+    let ct = client();
     let correct_key = path.file_name().unwrap().to_str().unwrap().to_string();
 
     // Create multipart
@@ -536,9 +632,12 @@ pub fn put_multipart(bucket: String,
 /// * `-p`, `--prefix` 上传到指定前缀
 /// * `-s`, `--storageclass` 储存模式
 ///
+/// 所有线程共用同一个 `CTClient`（凭证与连接池）
+///
 /// ### 截图
 /// ![object-up-mutilthread.png](https://i.loli.net/2017/11/21/5a13b99533148.png)
 pub fn put_multithread(
+    ct: &CTClient,
     bucket: String,
     path: &Path,
     prefix: String,
@@ -557,9 +656,11 @@ pub fn put_multithread(
                         let bucket = bucket.clone();
                         let prefix = prefix.clone();
                         let storage_class = storage_class.clone();
+                        let ct = ct.clone();
 
                         threads.push(thread::spawn(move || -> i32 {
                             put_multithread(
+                                &ct,
                                 bucket,
                                 entry.path().as_ref(),
                                 prefix,
@@ -610,7 +711,7 @@ pub fn put_multithread(
     let hash = md5(request.body.unwrap());
 
     request.content_md5 = Some(hash);
-    match CTClient::default_client().put_object(&request, None) {
+    match ct.put_object(&request, None) {
         Ok(output) => {
             debug!("{:#?}", output);
             info!("{}\t{:?}", " ✓ ".green().bold(), path);
@@ -700,26 +801,34 @@ pub fn put_securely(
     // Compute hash - Hash is slow
     let hash = md5(request.body.unwrap());
 
-    match CTClient::default_securely_client(password, method).put_object_securely(request, None) {
+    match client().with_encryption(password, method).put_object_securely(request, None) {
         Ok(output) => {
             debug!("{:#?}", output);
             info!("{}\t{:?}", " ✓ ".green().bold(), path);
         }
         Err(err) => {
-            print_aws_err!(err);
+            print_err!(err);
             info!("{}\t{:?}", " ✗ ".red().bold(), path);
         }
     }
 }
 
-/// Post 上传文件
+/// Post 上传文件，输出签名后的表单字段
 ///
 /// ```shell
-/// ct-cli object <bucket> post <key>
+/// ct-cli object <bucket> post <keys>... [-p] [-e] [--maxsize] [-t]
+/// ct-cli object <bucket> post <keys>... --serve [-b] [-n] [--timeout]
 /// ```
 ///
 /// ### 选项
-/// * `-e`, `--expires` 有效期
+/// * `-p`, `--prefix` 将 key 作为前缀，上传时使用原文件名
+/// * `-e`, `--expires` 有效期（天）
+/// * `--maxsize` 文件大小上限（字节）
+/// * `-t`, `--contenttype` Content-Type 前缀
+/// * `--serve` 提供 HTML 上传页面，上传结果通过 `success_action_redirect` 返回本地服务
+/// * `-b`, `--bind` 监听地址（默认 127.0.0.1:3000），跳转地址取自浏览器访问时的 `Host`
+/// * `-n`, `--uploads` 上传 N 次后退出
+/// * `--timeout` 运行 N 秒后退出
 ///
 /// ### 截图
 /// ![object-post.png](https://i.loli.net/2017/11/24/5a17baf75e9d8.png)
@@ -727,19 +836,82 @@ pub fn put_securely(
 ///
 pub fn post(bucket: &str, args: &ArgMatches) {
     debug!("Post Object");
-    let key = args.value_of("key").unwrap();
-    let expires = match args.value_of("expires") {
-        Some(s) => Some(s.to_string()),
-        None => None,
-    };
+    let keys = args.values_of("keys").unwrap().collect::<Vec<_>>();
+    let bind = args.value_of("bind").unwrap_or("127.0.0.1:3000");
+    let serve = args.is_present("serve");
 
-    CTClient::default_client().post_object(&PostObjectRequest {
-        bucket: bucket.to_string(),
-        key: key.to_string(),
-        expires,
-    });
-}
+    let mut template = PostPolicy::new(bucket);
+    if args.is_present("expires") {
+        let days = value_t!(args, "expires", i64).unwrap_or_else(|e| e.exit());
+        template = template.expires(Duration::days(days));
+    }
+    if args.is_present("max_size") {
+        let max_size = value_t!(args, "max_size", u64).unwrap_or_else(|e| e.exit());
+        template = template.content_length_range(0, max_size);
+    }
+    if let Some(content_type) = args.value_of("content_type") {
+        template = template.content_type_prefix(content_type);
+    }
+
+    let ct = client();
+    let mut forms = Vec::new();
+    let mut policies = Vec::new();
+
+    for key in keys {
+        let policy = match args.is_present("prefix") {
+            true => template.clone().key_prefix(key),
+            false => template.clone().key(key),
+        };
+        // 服务模式下表单在打开页面时重新签名，这里只检查能否签名
+        match ct.post_object(&policy) {
+            Ok(out) => {
+                debug!("{:#?}", out);
+                forms.push((key.to_string(), out));
+                policies.push((key.to_string(), policy));
+            }
+            Err(err) => {
+                print_err!(err);
+                info!("{}\t{}", " ✗ ".red().bold(), key);
+            }
+        }
+    }
 
+    if serve {
+        let max_uploads = match args.is_present("uploads") {
+            true => Some(value_t!(args, "uploads", usize).unwrap_or_else(|e| e.exit())),
+            false => None,
+        };
+        let timeout = match args.is_present("timeout") {
+            true => Some(StdDuration::from_secs(
+                value_t!(args, "timeout", u64).unwrap_or_else(|e| e.exit()),
+            )),
+            false => None,
+        };
+        serve_forms(
+            ct,
+            policies,
+            &ServeOptions {
+                bind: bind.to_string(),
+                max_uploads,
+                timeout,
+            },
+        );
+        return;
+    }
+
+    for (key, out) in forms {
+        info!("{}\t{}", key, out.url);
+        let mut table = Table::init(
+            out.fields
+                .iter()
+                .map(|&(ref name, ref value)| {
+                    Row::new(vec![Cell::new(name), Cell::new(value)])
+                })
+                .collect(),
+        );
+        table!(table, name, value);
+    }
+}
 
 /// 删除对象（Delete）
 ///
@@ -754,7 +926,7 @@ pub fn delete(bucket: &str, args: &ArgMatches) {
     let count = args.occurrences_of("keys");
     let keys = args.values_of("keys").unwrap().collect::<Vec<_>>();
 
-    let ct = CTClient::default_client();
+    let ct = client();
 
     let mut success = 0;
     let mut error = 0;
@@ -790,6 +962,133 @@ pub fn delete(bucket: &str, args: &ArgMatches) {
     )
 }
 
+/// 恢复对象的历史版本
+///
+/// ```shell
+/// ct-cli object <bucket> restore <key> <version_id>
+/// ```
+///
+/// 在服务端将该版本复制为最新版本，较新的版本仍然保留；也可用于撤销删除。
+pub fn restore(bucket: &str, args: &ArgMatches) {
+    debug!("Restore Object");
+    let key = args.value_of("key").unwrap();
+    let version_id = args.value_of("version_id").unwrap();
+
+    match client().restore_object_version(bucket, key, version_id) {
+        Ok(output) => {
+            debug!("{:#?}", output);
+            info!("{}\t{}", " ✓ ".green().bold(), key);
+        }
+        Err(err) => {
+            print_err!(err);
+            info!("{}\t{}", " ✗ ".red().bold(), key);
+        }
+    }
+}
+
+/// 复制对象，在服务端完成，不经过本地
+///
+/// ```shell
+/// ct-cli object <bucket> cp <source> <target> [-b <target_bucket>] [--version-id <version_id>]
+///     [-t <content_type>] [-m <name=value>...] [-s <storage_class>] [--ifmatch <etag>]
+///     [--ifnonematch <etag>]
+/// ```
+///
+/// `target` 以 `/` 结尾时保留源对象的文件名；超过 5 GB 的对象自动分片复制。
+///
+/// ### 选项
+/// * `-b`, `--bucket` 目标仓库，默认为同一仓库
+/// * `--version-id`, `--versionid` 复制指定版本
+/// * `-t`, `--contenttype` 替换 Content-Type
+/// * `-m`, `--meta` 替换自定义元数据（`name=value`），与 `-t` 都未指定时保留源对象的元数据
+/// * `-s`, `--storageclass` 储存模式
+/// * `--ifmatch` 仅在源对象 ETag 相同时复制
+/// * `--ifnonematch` 仅在源对象 ETag 不同时复制
+pub fn copy(bucket: &str, args: &ArgMatches) {
+    debug!("Copy Object");
+    let input = match copy_request(bucket, args) {
+        Ok(input) => input,
+        Err(err) => {
+            error!("{}", err);
+            return;
+        }
+    };
+
+    match client().copy_object(&input) {
+        Ok(output) => {
+            debug!("{:#?}", output);
+            info!("{}\t{} -> {}", " ✓ ".green().bold(), input.source_key, input.key);
+        }
+        Err(err) => {
+            print_err!(err);
+            info!("{}\t{} -> {}", " ✗ ".red().bold(), input.source_key, input.key);
+        }
+    }
+}
+
+/// 移动（重命名）对象，复制成功后删除源对象
+///
+/// ```shell
+/// ct-cli object <bucket> mv <source> <target> [-b <target_bucket>] [-t <content_type>]
+///     [-m <name=value>...] [-s <storage_class>] [--ifmatch <etag>] [--ifnonematch <etag>]
+/// ```
+///
+/// 选项同 [`cp`](fn.copy.html)。只删除被复制的源对象：开启多版本的仓库中永久删除被复制的版本
+/// （不留删除标记），否则源对象的 ETag 改变时保留源对象。不能移动到自身，除非指定 `--version-id`。
+pub fn rename(bucket: &str, args: &ArgMatches) {
+    debug!("Move Object");
+    let input = match copy_request(bucket, args) {
+        Ok(input) => input,
+        Err(err) => {
+            error!("{}", err);
+            return;
+        }
+    };
+
+    match client().move_object(&input) {
+        Ok(output) => {
+            debug!("{:#?}", output);
+            info!("{}\t{} -> {}", " ✓ ".green().bold(), input.source_key, input.key);
+        }
+        Err(err) => {
+            print_err!(err);
+            info!("{}\t{} -> {}", " ✗ ".red().bold(), input.source_key, input.key);
+        }
+    }
+}
+
+/// 由 `cp`、`mv` 的参数生成 `ObjectCopyRequest`
+fn copy_request(bucket: &str, args: &ArgMatches) -> Result<ObjectCopyRequest, String> {
+    let source = args.value_of("source").unwrap();
+    let target_bucket = args.value_of("target_bucket").unwrap_or(bucket);
+    let mut target = args.value_of("target").unwrap().to_string();
+    if target.ends_with('/') {
+        target.push_str(source.rsplit('/').next().unwrap_or(source));
+    }
+
+    let mut input = ObjectCopyRequest::new(bucket, source, target_bucket, &target);
+    input.source_version_id = args.value_of("version_id").map(|v| v.to_string());
+    input.content_type = args.value_of("content_type").map(|v| v.to_string());
+    input.storage_class = args.value_of("storage_class").map(|v| v.to_string());
+    input.if_match = args.value_of("if_match").map(|v| v.to_string());
+    input.if_none_match = args.value_of("if_none_match").map(|v| v.to_string());
+    if let Some(metadata) = args.values_of("metadata") {
+        for meta in metadata {
+            let mut pair = meta.splitn(2, '=');
+            match (pair.next(), pair.next()) {
+                (Some(name), Some(value)) if !name.is_empty() => {
+                    input.metadata.push((name.to_string(), value.to_string()))
+                }
+                _ => return Err(format!("invalid metadata {:?}, expected name=value", meta)),
+            }
+        }
+    }
+    if input.content_type.is_some() || !input.metadata.is_empty() {
+        input.metadata_directive = MetadataDirective::Replace;
+    }
+    Ok(input)
+}
+
 /// 分享对象（share, presign）
 ///
 /// ```shell
@@ -808,12 +1107,43 @@ pub fn share(bucket: &str, args: &ArgMatches) {
         None => None,
     };
 
-    match CTClient::default_client().presigned_object(&PresignedObjectRequest {
+    match client().presigned_object(&PresignedObjectRequest {
         bucket: bucket.to_string(),
         key: key.to_string(),
         expires,
     }) {
         Ok(h) => info!("{}", h),
-        Err(err) => print_aws_err!(err),
+        Err(err) => print_err!(err),
+    }
+}
+
+/// 管理对象授权
+///
+/// ```shell
+/// ct-cli object <bucket> grant <key> get
+/// ct-cli object <bucket> grant <key> set [--noowner] <grants>...
+/// ```
+///
+/// 授权格式同 [`bucket grant`](../bucket/fn.grant.html)
+pub fn grant(bucket: &str, args: &ArgMatches) {
+    debug!("Object Grants");
+    let key = args.value_of("key").unwrap();
+    let ct = client();
+
+    let current = ct.get_object_grants(bucket, key);
+    match (args.subcommand(), current) {
+        (("get", _), Ok(acl)) => print_grants(acl),
+        (("set", Some(args)), Ok(acl)) => match read_grants(args, acl.owner) {
+            Ok(acl) => match ct.put_object_grants(bucket, key, &acl) {
+                Ok(_) => info!("{}\t{}", key, " ✓ ".green().bold()),
+                Err(err) => {
+                    print_err!(err);
+                    info!("{}\t{}", key, " ✗ ".red().bold());
+                }
+            },
+            Err(err) => print_err!(err),
+        },
+        (_, Err(err)) => print_err!(err),
+        _ => {}
     }
 }
@@ -0,0 +1,267 @@
+// Copyright 2017 Gitai<i@gitai.me> All rights reserved.
+//
+// Permission is hereby granted, free of charge, to any person
+// obtaining a copy of this software and associated documentation
+// files (the "Software"), to deal in the Software without restriction,
+// including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall
+// be included in all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND,
+// EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES
+// OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.
+// IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR
+// ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF
+// CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION
+// WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+//! POST 上传辅助服务
+//!
+//! 在本地提供 HTML 上传页面，上传成功后 OOS 通过 `success_action_redirect`
+//! 跳转回本服务的 `/result`，用于统计上传结果。
+//!
+//! 表单在每次打开页面时签名，跳转地址取自请求的 `Host`，监听 `0.0.0.0` 时
+//! 其他机器的浏览器也能跳转回来。
+
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{channel, RecvTimeoutError, Sender};
+use std::time::{Duration, Instant};
+
+use colored::*;
+
+use hyper::Server;
+use hyper::header::{ContentType, Host};
+use hyper::server::{Request, Response};
+use hyper::status::StatusCode;
+use hyper::uri::RequestUri;
+
+use url::Url;
+
+use ct_sdk::ct::object::{CTClientObject, PostKey, PostObjectOutput, PostPolicy};
+use ct_sdk::ct::sdk::CTClient;
+
+/// 服务配置
+pub struct ServeOptions {
+    /// 监听地址，例如 `127.0.0.1:3000`
+    pub bind: String,
+    /// 上传成功 N 次后退出
+    pub max_uploads: Option<usize>,
+    /// 超时后退出
+    pub timeout: Option<Duration>,
+}
+
+/// OOS 跳转回来的上传结果
+#[derive(Debug, Default)]
+pub struct UploadResult {
+    pub bucket: String,
+    pub key: String,
+    pub etag: String,
+}
+
+impl UploadResult {
+    /// 是否为某个表单上传的对象
+    fn is_valid(&self, policies: &[(String, PostPolicy)]) -> bool {
+        !self.etag.is_empty() && policies.iter().any(|&(_, ref policy)| {
+            policy.bucket == self.bucket && match policy.key {
+                Some(PostKey::Exact(ref key)) => *key == self.key,
+                Some(PostKey::StartsWith(ref prefix)) => self.key.starts_with(prefix.as_str()),
+                None => !self.key.is_empty(),
+            }
+        })
+    }
+}
+
+/// 跳转地址，`host` 为浏览器访问本服务时使用的地址
+fn redirect_url(host: &str) -> String {
+    format!("http://{}/result", host)
+}
+
+/// 启动本地服务，直到上传次数或时间达到上限（未设置时一直运行）
+pub fn serve(client: CTClient, policies: Vec<(String, PostPolicy)>, options: &ServeOptions) {
+    let (tx, rx) = channel::<UploadResult>();
+    let tx = Mutex::new(tx);
+    let policies = Arc::new(policies);
+    let bind = options.bind.clone();
+
+    let mut listening = match Server::http(options.bind.as_str()).and_then(|server| {
+        server.handle(move |req: Request, res: Response| {
+            handle(&client, &policies, &bind, &tx, req, res)
+        })
+    }) {
+        Ok(listening) => listening,
+        Err(err) => {
+            error!("Bind {} failed: {}", options.bind, err);
+            return;
+        }
+    };
+    info!("Open http://{} in browser for upload file", options.bind);
+
+    let deadline = options.timeout.map(|timeout| Instant::now() + timeout);
+    let mut uploaded = 0;
+
+    loop {
+        let received = match deadline {
+            Some(deadline) => {
+                let now = Instant::now();
+                if now >= deadline {
+                    Err(RecvTimeoutError::Timeout)
+                } else {
+                    rx.recv_timeout(deadline - now)
+                }
+            }
+            None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+
+        match received {
+            Ok(result) => {
+                debug!("{:#?}", result);
+                info!("{}\t{}/{}", " ✓ ".green().bold(), result.bucket, result.key);
+                uploaded += 1;
+                if options.max_uploads.map_or(false, |max| uploaded >= max) {
+                    break;
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                info!("Timeout");
+                break;
+            }
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+
+    info!("\nUploaded: {}", format!("{}", uploaded).green());
+    let _ = listening.close();
+}
+
+fn handle(
+    client: &CTClient,
+    policies: &[(String, PostPolicy)],
+    bind: &str,
+    tx: &Mutex<Sender<UploadResult>>,
+    req: Request,
+    mut res: Response,
+) {
+    let path = match req.uri {
+        RequestUri::AbsolutePath(ref path) => path.clone(),
+        _ => "/".to_string(),
+    };
+    let url = match Url::parse(&format!("http://localhost{}", path)) {
+        Ok(url) => url,
+        Err(_) => return,
+    };
+
+    res.headers_mut().set(ContentType::html());
+
+    let body = match url.path() {
+        "/result" => {
+            let mut result = UploadResult::default();
+            for (name, value) in url.query_pairs() {
+                match name.as_ref() {
+                    "bucket" => result.bucket = value.into_owned(),
+                    "key" => result.key = value.into_owned(),
+                    "etag" => result.etag = value.into_owned(),
+                    _ => {}
+                }
+            }
+            if !result.is_valid(policies) {
+                debug!("Invalid upload result: {:?}", url.query());
+                *res.status_mut() = StatusCode::BadRequest;
+                let _ = res.send(b"<html><body><p>Invalid upload result</p></body></html>");
+                return;
+            }
+            let body = format!(
+                "<html><body><p>Uploaded {} ({})</p><a href=\"/\">Back</a></body></html>",
+                escape_html(&result.key),
+                escape_html(&result.etag)
+            );
+            let _ = tx.lock().unwrap().send(result);
+            body
+        }
+        _ => {
+            let host = match req.headers.get::<Host>() {
+                Some(&Host { ref hostname, port: Some(port) }) => format!("{}:{}", hostname, port),
+                Some(&Host { ref hostname, port: None }) => hostname.clone(),
+                None => bind.to_string(),
+            };
+            render_page(client, policies, &redirect_url(&host))
+        }
+    };
+
+    let _ = res.send(body.as_bytes());
+}
+
+/// 每个对象 ID 一个表单，上传成功后跳转到 `redirect`
+fn render_page(client: &CTClient, policies: &[(String, PostPolicy)], redirect: &str) -> String {
+    let body = policies
+        .iter()
+        .map(|&(ref title, ref policy)| {
+            let policy = policy.clone().success_action_redirect(redirect);
+            let form = match client.post_object(&policy) {
+                Ok(form) => render_form(&form),
+                Err(err) => format!("<p>{}</p>", escape_html(&err.to_string())),
+            };
+            format!("<h3>{}</h3>\n{}", escape_html(title), form)
+        })
+        .collect::<Vec<_>>()
+        .join("<hr/>\n");
+
+    format!(
+        r#"
+        <html>
+          <head>
+            <meta http-equiv="Content-Type" content="text/html; charset=UTF-8" />
+          </head>
+          <body>
+          {}
+          </body>
+        </html>
+    "#,
+        body
+    )
+}
+
+/// 将签名字段渲染为 HTML 表单
+fn render_form(out: &PostObjectOutput) -> String {
+    let mut inputs = String::new();
+    for &(ref name, ref value) in &out.fields {
+        let kind = match name.as_str() {
+            "key" | "Content-Type" => "text",
+            _ => "hidden",
+        };
+        inputs.push_str(&format!(
+            "{}<input type=\"{}\" name=\"{}\" value=\"{}\" /><br/>\n",
+            match kind {
+                "text" => format!("{}: ", name),
+                _ => String::new(),
+            },
+            kind,
+            escape_html(name),
+            escape_html(value)
+        ));
+    }
+
+    format!(
+        r#"
+          <form action="{}" method="post" enctype="multipart/form-data">
+            {}
+            File: <input type="file" name="file"/>
+            <br/>
+            <input type="submit" name="submit" value="Upload" />
+          </form>
+    "#,
+        escape_html(&out.url),
+        inputs
+    )
+}
+
+fn escape_html(input: &str) -> String {
+    input
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
@@ -0,0 +1,174 @@
+// Copyright 2017 Gitai<i@gitai.me> All rights reserved.
+//
+// Permission is hereby granted, free of charge, to any person
+// obtaining a copy of this software and associated documentation
+// files (the "Software"), to deal in the Software without restriction,
+// including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall
+// be included in all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND,
+// EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES
+// OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.
+// IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR
+// ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF
+// CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION
+// WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+use std::fs::File;
+use std::io::Write;
+
+use chrono::{Datelike, Local, NaiveDate};
+use rustc_serialize::Encodable;
+use rustc_serialize::json;
+
+use ct_sdk::ct::stats::*;
+
+use prettytable::Table;
+use prettytable::row::Row;
+use prettytable::cell::Cell;
+use prettytable::format::FormatBuilder;
+
+use colored::*;
+
+use clap::ArgMatches;
+
+use cli::client;
+
+/// 统计仓库的容量、流量和请求数
+///
+/// ```shell
+/// $ ct-cli stats [bucket] [--from 2017-12-01] [--to 2017-12-31] [--byhour] [-o usage.csv]
+/// ```
+/// ### 选项
+/// * `bucket`: 只统计该仓库，默认统计全部仓库
+/// * `--from`: 开始日期，默认为本月第一天
+/// * `--to`: 结束日期（包含），默认为今天
+/// * `--byhour`: 按小时列出每个仓库的统计，默认列出整个时间段的合计
+/// * `--host`: 统计接口的域名，默认为 `oos-cn-mg.ctyunapi.cn`
+/// * `-o`, `--output`: 导出到文件，`.json` 导出 JSON，其他导出 CSV
+pub fn stats(args: &ArgMatches) {
+    debug!("Usage Statistics");
+    let today = Local::today().naive_local();
+
+    let begin_date = match args.value_of("from") {
+        Some(date) => match parse_date(date) {
+            Some(date) => date,
+            None => return,
+        },
+        None => NaiveDate::from_ymd(today.year(), today.month(), 1),
+    };
+    let end_date = match args.value_of("to") {
+        Some(date) => match parse_date(date) {
+            Some(date) => date,
+            None => return,
+        },
+        None => today,
+    };
+
+    let mut input = StatsRequest::new(begin_date, end_date);
+    input.bucket = args.value_of("bucket_name").map(|name| name.to_string());
+    input.host = args.value_of("host").map(|host| host.to_string());
+
+    match args.is_present("by_hour") {
+        true => {
+            input.freq = StatsFreq::ByHour;
+            match client().get_period_usage(&input) {
+                Ok(usage) => match args.value_of("output") {
+                    Some(path) => print_export(path, &usage),
+                    None => printstd!(usage, date, bucket, capacity, upload, download, requests),
+                },
+                Err(err) => print_err!(err),
+            }
+        }
+        false => match client().get_bucket_usage(&input) {
+            Ok(usage) => match args.value_of("output") {
+                Some(path) => print_export(path, &usage),
+                None => printstd!(usage, bucket, capacity, upload, download, requests),
+            },
+            Err(err) => print_err!(err),
+        },
+    }
+}
+
+fn parse_date(date: &str) -> Option<NaiveDate> {
+    match NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+        Ok(date) => Some(date),
+        Err(_) => {
+            error!("Invalid date {}, expected YYYY-MM-DD", date);
+            None
+        }
+    }
+}
+
+/// 导出的一行
+trait CsvRow {
+    fn header() -> &'static str;
+    fn csv(&self) -> String;
+}
+
+impl CsvRow for BucketUsage {
+    fn header() -> &'static str {
+        "bucket,capacity,upload,download,requests"
+    }
+
+    fn csv(&self) -> String {
+        format!(
+            "{},{},{},{},{}",
+            self.bucket,
+            self.capacity,
+            self.upload,
+            self.download,
+            self.requests
+        )
+    }
+}
+
+impl CsvRow for PeriodUsage {
+    fn header() -> &'static str {
+        "date,bucket,capacity,upload,download,requests"
+    }
+
+    fn csv(&self) -> String {
+        format!(
+            "{},{},{},{},{},{}",
+            self.date,
+            self.bucket,
+            self.capacity,
+            self.upload,
+            self.download,
+            self.requests
+        )
+    }
+}
+
+fn print_export<T: CsvRow + Encodable>(path: &str, usage: &[T]) {
+    match export(path, usage) {
+        Ok(_) => info!("{}\t{}", " ✓ ".green().bold(), path),
+        Err(err) => {
+            error!("{}", err);
+            info!("{}\t{}", " ✗ ".red().bold(), path);
+        }
+    }
+}
+
+/// 写入 JSON 或 CSV 文件
+fn export<T: CsvRow + Encodable>(path: &str, usage: &[T]) -> Result<(), String> {
+    let content = match path.ends_with(".json") {
+        true => format!("{}\n", json::as_pretty_json(&usage)),
+        false => {
+            let mut csv = format!("{}\n", T::header());
+            for row in usage {
+                csv.push_str(&row.csv());
+                csv.push('\n');
+            }
+            csv
+        }
+    };
+    let mut file = try!(File::create(path).map_err(|e| e.to_string()));
+    file.write_all(content.as_bytes()).map_err(|e| e.to_string())
+}
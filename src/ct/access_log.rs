@@ -0,0 +1,253 @@
+// Copyright 2017 Gitai<i@gitai.me> All rights reserved.
+//
+// Permission is hereby granted, free of charge, to any person
+// obtaining a copy of this software and associated documentation
+// files (the "Software"), to deal in the Software without restriction,
+// including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall
+// be included in all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND,
+// EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES
+// OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.
+// IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR
+// ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF
+// CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION
+// WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+//! Parser of the server access logs
+//!
+//! Each line of the log objects written by `put_bucket_logging` is a request:
+//!
+//! ```text
+//! owner photos [06/Feb/2018:00:00:38 +0000] 192.0.2.3 user 3E57427F3EXAMPLE REST.GET.OBJECT
+//! cat.jpg "GET /photos/cat.jpg HTTP/1.1" 200 - 2662992 3462992 70 10 "-" "curl/7.47.0" -
+//! ```
+//!
+//! Fields are separated by spaces, `[...]` and `"..."` fields may contain spaces
+//! and `-` stands for a missing value.
+
+use std::str::FromStr;
+
+use chrono::{DateTime, FixedOffset};
+
+use ct::error::CtError;
+
+/// A request of the access log
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessLogRecord {
+    pub bucket_owner: String,
+    pub bucket: String,
+    pub time: DateTime<FixedOffset>,
+    pub remote_ip: String,
+    /// `None` for anonymous requests
+    pub requester: Option<String>,
+    pub request_id: String,
+    /// e.g. `REST.GET.OBJECT`
+    pub operation: String,
+    pub key: Option<String>,
+    /// e.g. `GET /photos/cat.jpg HTTP/1.1`
+    pub request_uri: Option<String>,
+    pub http_status: Option<u16>,
+    pub error_code: Option<String>,
+    pub bytes_sent: Option<u64>,
+    pub object_size: Option<u64>,
+    /// Milliseconds
+    pub total_time: Option<u64>,
+    /// Milliseconds
+    pub turn_around_time: Option<u64>,
+    pub referrer: Option<String>,
+    pub user_agent: Option<String>,
+    pub version_id: Option<String>,
+}
+
+/// Split a line into fields, keeping the spaces inside `[...]` and `"..."`.
+fn fields(line: &str) -> Result<Vec<String>, CtError> {
+    let mut fields = Vec::new();
+    let mut chars = line.trim().chars().peekable();
+    loop {
+        while chars.peek() == Some(&' ') {
+            chars.next();
+        }
+        let close = match chars.peek() {
+            None => return Ok(fields),
+            Some(&'"') => Some('"'),
+            Some(&'[') => Some(']'),
+            Some(_) => None,
+        };
+        let mut field = String::new();
+        match close {
+            Some(close) => {
+                chars.next();
+                loop {
+                    match chars.next() {
+                        Some('\\') if close == '"' => if let Some(c) = chars.next() {
+                            field.push(c);
+                        },
+                        Some(c) if c == close => break,
+                        Some(c) => field.push(c),
+                        None => {
+                            return Err(CtError::Config(format!("unterminated {:?}", field)))
+                        }
+                    }
+                }
+            }
+            None => while let Some(&c) = chars.peek() {
+                if c == ' ' {
+                    break;
+                }
+                field.push(c);
+                chars.next();
+            },
+        }
+        fields.push(field);
+    }
+}
+
+fn optional(field: &str) -> Option<String> {
+    match field {
+        "-" | "" => None,
+        field => Some(field.to_string()),
+    }
+}
+
+fn number<T: FromStr>(field: &str, name: &str) -> Result<Option<T>, CtError> {
+    match field {
+        "-" | "" => Ok(None),
+        field => field
+            .parse()
+            .map(Some)
+            .map_err(|_| CtError::Config(format!("invalid {} {:?}", name, field))),
+    }
+}
+
+impl AccessLogRecord {
+    /// Parse a line of the log, the fields after `version_id` are ignored.
+    pub fn parse(line: &str) -> Result<AccessLogRecord, CtError> {
+        let fields = try!(fields(line));
+        if fields.len() < 17 {
+            return Err(CtError::Config(
+                format!("{} fields, at least 17 expected", fields.len()),
+            ));
+        }
+        let time = try!(
+            DateTime::parse_from_str(&fields[2], "%d/%b/%Y:%H:%M:%S %z").map_err(|err| {
+                CtError::Config(format!("invalid time {:?}: {}", fields[2], err))
+            })
+        );
+        Ok(AccessLogRecord {
+            bucket_owner: fields[0].clone(),
+            bucket: fields[1].clone(),
+            time: time,
+            remote_ip: fields[3].clone(),
+            requester: optional(&fields[4]),
+            request_id: fields[5].clone(),
+            operation: fields[6].clone(),
+            key: optional(&fields[7]),
+            request_uri: optional(&fields[8]),
+            http_status: try!(number(&fields[9], "HTTP status")),
+            error_code: optional(&fields[10]),
+            bytes_sent: try!(number(&fields[11], "bytes sent")),
+            object_size: try!(number(&fields[12], "object size")),
+            total_time: try!(number(&fields[13], "total time")),
+            turn_around_time: try!(number(&fields[14], "turn around time")),
+            referrer: optional(&fields[15]),
+            user_agent: optional(&fields[16]),
+            version_id: fields.get(17).and_then(|field| optional(field)),
+        })
+    }
+}
+
+impl FromStr for AccessLogRecord {
+    type Err = CtError;
+
+    fn from_str(line: &str) -> Result<AccessLogRecord, CtError> {
+        AccessLogRecord::parse(line)
+    }
+}
+
+/// The records of a log object and the lines that could not be parsed
+#[derive(Debug, Default)]
+pub struct AccessLog {
+    pub records: Vec<AccessLogRecord>,
+    /// Line numbers, starting from 1, and their errors
+    pub invalid_lines: Vec<(usize, CtError)>,
+}
+
+/// Parse the lines of a log object, skipping the empty ones.
+///
+/// An invalid line doesn't stop the parsing, it is reported in `invalid_lines`.
+pub fn parse_access_log(log: &str) -> AccessLog {
+    let mut access_log = AccessLog::default();
+    for (index, line) in log.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match AccessLogRecord::parse(line) {
+            Ok(record) => access_log.records.push(record),
+            Err(err) => access_log.invalid_lines.push((index + 1, err)),
+        }
+    }
+    access_log
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_records() {
+        let log = "owner photos [06/Feb/2018:00:00:38 +0800] 192.0.2.3 user \
+                   3E57427F3EXAMPLE REST.GET.OBJECT cat.jpg \
+                   \"GET /photos/cat.jpg HTTP/1.1\" 200 - 2662992 3462992 70 10 \
+                   \"http://www.example.com/\" \"curl/7.47.0 (x86_64)\" -\n\
+                   \n\
+                   owner photos [06/Feb/2018:00:01:00 +0800] 192.0.2.4 - \
+                   3E57427F3EXAMPLF REST.PUT.OBJECT - \"PUT /photos/ HTTP/1.1\" \
+                   403 AccessDenied 243 - 4 - \"-\" \"say \\\"hi\\\"\"\n";
+        let access_log = parse_access_log(log);
+        assert!(access_log.invalid_lines.is_empty());
+        let records = access_log.records;
+        assert_eq!(records.len(), 2);
+
+        let get = &records[0];
+        assert_eq!(get.time.to_rfc3339(), "2018-02-06T00:00:38+08:00");
+        assert_eq!(get.key, Some(String::from("cat.jpg")));
+        assert_eq!(get.request_uri, Some(String::from("GET /photos/cat.jpg HTTP/1.1")));
+        assert_eq!(get.http_status, Some(200));
+        assert_eq!(get.bytes_sent, Some(2662992));
+        assert_eq!(get.user_agent, Some(String::from("curl/7.47.0 (x86_64)")));
+        assert_eq!(get.version_id, None);
+
+        let put = &records[1];
+        assert_eq!(put.requester, None);
+        assert_eq!(put.error_code, Some(String::from("AccessDenied")));
+        assert_eq!(put.object_size, None);
+        assert_eq!(put.user_agent, Some(String::from("say \"hi\"")));
+
+        assert!(AccessLogRecord::parse("owner photos [06/Feb/2018 192.0.2.3").is_err());
+    }
+
+    #[test]
+    fn invalid_lines() {
+        let log = "owner photos [06/Feb/2018:00:00:38 +0800] 192.0.2.3\n\
+                   owner photos [06/Feb/2018:00:01:00 +0800] 192.0.2.4 - \
+                   3E57427F3EXAMPLF REST.PUT.OBJECT - \"PUT /photos/ HTTP/1.1\" \
+                   403 AccessDenied 243 - 4 - \"-\" \"-\"\n\
+                   \n\
+                   owner photos [06/Feb/2018:00:02:00 +0800] 192.0.2.5 - \
+                   3E57427F3EXAMPLG REST.GET.OBJECT cat.jpg \"GET /photos/cat.jpg HTTP/1.1\" \
+                   OK - - - - - \"-\" \"-\"\n";
+        let access_log = parse_access_log(log);
+        assert_eq!(access_log.records.len(), 1);
+        assert_eq!(access_log.records[0].request_id, "3E57427F3EXAMPLF");
+
+        let lines: Vec<usize> = access_log.invalid_lines.iter().map(|&(line, _)| line).collect();
+        assert_eq!(lines, vec![1, 4]);
+        assert!(format!("{}", access_log.invalid_lines[1].1).contains("HTTP status"));
+    }
+}
@@ -0,0 +1,409 @@
+// Copyright 2017 Gitai<i@gitai.me> All rights reserved.
+//
+// Permission is hereby granted, free of charge, to any person
+// obtaining a copy of this software and associated documentation
+// files (the "Software"), to deal in the Software without restriction,
+// including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall
+// be included in all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND,
+// EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES
+// OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.
+// IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR
+// ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF
+// CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION
+// WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+//! Access control lists of buckets and objects
+//!
+//! Unlike the canned ACLs, an `AccessControl` lists every grant explicitly.
+//! Grants are written `<type>:<grantee>=<permission>` on the command line, see
+//! `AclGrant::from_str`.
+
+use std::fmt::{Display, Error, Formatter};
+use std::str::FromStr;
+
+use ct::sdk::CTClient;
+use ct::error::CtError;
+use ct::decode::{decode, Element, FromXml, XmlError};
+use ct::bucket::{push_element, subresource_call};
+
+/// Group of every user, including anonymous ones
+pub const ALL_USERS: &'static str = "http://acs.amazonaws.com/groups/global/AllUsers";
+
+/// Group of the users with credentials
+pub const AUTHENTICATED_USERS: &'static str =
+    "http://acs.amazonaws.com/groups/global/AuthenticatedUsers";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AclPermission {
+    FullControl,
+    Read,
+    Write,
+    ReadAcp,
+    WriteAcp,
+}
+
+impl Display for AclPermission {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match self {
+            &AclPermission::FullControl => write!(f, "FULL_CONTROL"),
+            &AclPermission::Read => write!(f, "READ"),
+            &AclPermission::Write => write!(f, "WRITE"),
+            &AclPermission::ReadAcp => write!(f, "READ_ACP"),
+            &AclPermission::WriteAcp => write!(f, "WRITE_ACP"),
+        }
+    }
+}
+
+impl FromStr for AclPermission {
+    type Err = CtError;
+
+    fn from_str(permission: &str) -> Result<AclPermission, CtError> {
+        match permission.to_uppercase().replace('-', "_").as_str() {
+            "FULL_CONTROL" => Ok(AclPermission::FullControl),
+            "READ" => Ok(AclPermission::Read),
+            "WRITE" => Ok(AclPermission::Write),
+            "READ_ACP" => Ok(AclPermission::ReadAcp),
+            "WRITE_ACP" => Ok(AclPermission::WriteAcp),
+            _ => Err(CtError::Config(format!("unknown permission {:?}", permission))),
+        }
+    }
+}
+
+impl FromXml for AclPermission {
+    fn from_xml(element: &Element, path: &str) -> Result<AclPermission, XmlError> {
+        element
+            .text
+            .trim()
+            .parse()
+            .map_err(|err: CtError| XmlError::new(path, &format!("{}", err)))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AclGrantee {
+    /// A canonical user, `display_name` is ignored when writing
+    User { id: String, display_name: String },
+    Email(String),
+    /// A group by URI, e.g. `ALL_USERS`
+    Group(String),
+}
+
+impl AclGrantee {
+    pub fn user(id: &str) -> AclGrantee {
+        AclGrantee::User {
+            id: id.to_string(),
+            display_name: String::new(),
+        }
+    }
+
+    /// `CanonicalUser`, `AmazonCustomerByEmail` or `Group`
+    pub fn kind(&self) -> &'static str {
+        match self {
+            &AclGrantee::User { .. } => "CanonicalUser",
+            &AclGrantee::Email(_) => "AmazonCustomerByEmail",
+            &AclGrantee::Group(_) => "Group",
+        }
+    }
+}
+
+impl Display for AclGrantee {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match self {
+            &AclGrantee::User { ref id, ref display_name } => match display_name.is_empty() {
+                true => write!(f, "{}", id),
+                false => write!(f, "{} ({})", id, display_name),
+            },
+            &AclGrantee::Email(ref email) => write!(f, "{}", email),
+            &AclGrantee::Group(ref uri) => write!(f, "{}", uri.rsplit('/').next().unwrap_or(uri)),
+        }
+    }
+}
+
+impl FromXml for AclGrantee {
+    fn from_xml(element: &Element, path: &str) -> Result<AclGrantee, XmlError> {
+        let text = |name: &str| element.child(name).map(|child| child.text.trim().to_string());
+        match (text("ID"), text("EmailAddress"), text("URI")) {
+            (Some(id), _, _) => Ok(AclGrantee::User {
+                id: id,
+                display_name: text("DisplayName").unwrap_or(String::new()),
+            }),
+            (None, Some(email), _) => Ok(AclGrantee::Email(email)),
+            (None, None, Some(uri)) => Ok(AclGrantee::Group(uri)),
+            _ => Err(XmlError::new(path, "grantee without ID, EmailAddress nor URI")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AclGrant {
+    pub grantee: AclGrantee,
+    pub permission: AclPermission,
+}
+
+impl AclGrant {
+    pub fn new(grantee: AclGrantee, permission: AclPermission) -> AclGrant {
+        AclGrant {
+            grantee: grantee,
+            permission: permission,
+        }
+    }
+}
+
+impl Display for AclGrant {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "{}={}", self.grantee, self.permission)
+    }
+}
+
+/// Parse `id:<ID>=<permission>`, `email:<address>=<permission>`,
+/// `group:<all|authenticated|URI>=<permission>`.
+impl FromStr for AclGrant {
+    type Err = CtError;
+
+    fn from_str(grant: &str) -> Result<AclGrant, CtError> {
+        let invalid = || {
+            CtError::Config(format!(
+                "invalid grant {:?}, expected id|email|group:<grantee>=<permission>",
+                grant
+            ))
+        };
+        let (grantee, permission) = match grant.rfind('=') {
+            Some(index) => (&grant[..index], &grant[index + 1..]),
+            None => return Err(invalid()),
+        };
+        let (kind, name) = match grantee.find(':') {
+            Some(index) => (&grantee[..index], &grantee[index + 1..]),
+            None => return Err(invalid()),
+        };
+        if name.is_empty() {
+            return Err(invalid());
+        }
+        let grantee = match kind {
+            "id" => AclGrantee::user(name),
+            "email" => AclGrantee::Email(name.to_string()),
+            "group" => AclGrantee::Group(match name {
+                "all" => ALL_USERS.to_string(),
+                "authenticated" => AUTHENTICATED_USERS.to_string(),
+                uri => uri.to_string(),
+            }),
+            _ => return Err(invalid()),
+        };
+        Ok(AclGrant::new(grantee, try!(permission.parse())))
+    }
+}
+
+impl FromXml for AclGrant {
+    fn from_xml(element: &Element, path: &str) -> Result<AclGrant, XmlError> {
+        let child = |name: &str| match element.child(name) {
+            Some(child) => Ok((child, format!("{}/{}", path, name))),
+            None => Err(XmlError::new(path, &format!("missing element {}", name))),
+        };
+        let (grantee, grantee_path) = try!(child("Grantee"));
+        let (permission, permission_path) = try!(child("Permission"));
+        Ok(AclGrant {
+            grantee: try!(AclGrantee::from_xml(grantee, &grantee_path)),
+            permission: try!(AclPermission::from_xml(permission, &permission_path)),
+        })
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct AclOwner {
+    pub id: String,
+    pub display_name: String,
+}
+
+xml_struct! {
+    AclOwner {
+        "ID" => id,
+        "DisplayName" => display_name,
+    }
+}
+
+/// The `AccessControlPolicy` of a bucket or an object
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct AccessControl {
+    pub owner: AclOwner,
+    pub grants: Vec<AclGrant>,
+}
+
+xml_struct! {
+    AccessControl {
+        "Owner" => owner,
+        "AccessControlList" => grants,
+    }
+}
+
+impl AccessControl {
+    /// Permissions granted to `grantee`
+    pub fn permissions(&self, grantee: &AclGrantee) -> Vec<AclPermission> {
+        self.grants
+            .iter()
+            .filter(|grant| grant.grantee == *grantee)
+            .map(|grant| grant.permission)
+            .collect()
+    }
+
+    /// Grant `FULL_CONTROL` to the owner unless the grants already do.
+    pub fn keep_owner(&mut self) {
+        let owner = AclGrantee::user(&self.owner.id);
+        let granted = self.grants.iter().any(|grant| match grant.grantee {
+            AclGrantee::User { ref id, .. } => {
+                *id == self.owner.id && grant.permission == AclPermission::FullControl
+            }
+            _ => false,
+        });
+        if !granted {
+            self.grants.insert(0, AclGrant::new(owner, AclPermission::FullControl));
+        }
+    }
+
+    /// The `AccessControlPolicy` document
+    pub fn to_xml(&self) -> String {
+        let mut xml = String::from("<AccessControlPolicy><Owner>");
+        push_element(&mut xml, "ID", &self.owner.id);
+        if !self.owner.display_name.is_empty() {
+            push_element(&mut xml, "DisplayName", &self.owner.display_name);
+        }
+        xml.push_str("</Owner><AccessControlList>");
+        for grant in &self.grants {
+            xml.push_str(&format!(
+                "<Grant><Grantee xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\" \
+                 xsi:type=\"{}\">",
+                grant.grantee.kind()
+            ));
+            match grant.grantee {
+                AclGrantee::User { ref id, .. } => push_element(&mut xml, "ID", id),
+                AclGrantee::Email(ref email) => push_element(&mut xml, "EmailAddress", email),
+                AclGrantee::Group(ref uri) => push_element(&mut xml, "URI", uri),
+            }
+            xml.push_str("</Grantee>");
+            push_element(&mut xml, "Permission", grant.permission);
+            xml.push_str("</Grant>");
+        }
+        xml.push_str("</AccessControlList></AccessControlPolicy>");
+        xml
+    }
+}
+
+/// Explicit grants of buckets and objects for CTClient.
+pub trait CTClientAcl {
+    fn get_bucket_grants(&self, bucket: &str) -> Result<AccessControl, CtError>;
+    /// Replace the grants of a bucket.
+    fn put_bucket_grants(&self, bucket: &str, acl: &AccessControl) -> Result<(), CtError>;
+    fn get_object_grants(&self, bucket: &str, key: &str) -> Result<AccessControl, CtError>;
+    /// Replace the grants of an object.
+    fn put_object_grants(
+        &self,
+        bucket: &str,
+        key: &str,
+        acl: &AccessControl,
+    ) -> Result<(), CtError>;
+}
+
+impl CTClientAcl for CTClient {
+    fn get_bucket_grants(&self, bucket: &str) -> Result<AccessControl, CtError> {
+        self.get_object_grants(bucket, "")
+    }
+
+    fn put_bucket_grants(&self, bucket: &str, acl: &AccessControl) -> Result<(), CtError> {
+        self.put_object_grants(bucket, "", acl)
+    }
+
+    fn get_object_grants(&self, bucket: &str, key: &str) -> Result<AccessControl, CtError> {
+        let body = try!(subresource_call(self, "GET", bucket, key, "acl", None));
+        Ok(try!(decode(&body, &[])))
+    }
+
+    fn put_object_grants(
+        &self,
+        bucket: &str,
+        key: &str,
+        acl: &AccessControl,
+    ) -> Result<(), CtError> {
+        let payload = acl.to_xml().into_bytes();
+        try!(subresource_call(
+            self,
+            "PUT",
+            bucket,
+            key,
+            "acl",
+            Some((&payload, "application/xml"))
+        ));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ct::decode::decode;
+    use super::*;
+
+    #[test]
+    fn access_control() {
+        let body = r#"<?xml version="1.0" encoding="UTF-8"?>
+<AccessControlPolicy xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+  <Owner><ID>owner</ID><DisplayName>gitai</DisplayName></Owner>
+  <AccessControlList>
+    <Grant>
+      <Grantee xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:type="CanonicalUser">
+        <ID>owner</ID><DisplayName>gitai</DisplayName>
+      </Grantee>
+      <Permission>FULL_CONTROL</Permission>
+    </Grant>
+    <Grant>
+      <Grantee xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:type="Group">
+        <URI>http://acs.amazonaws.com/groups/global/AllUsers</URI>
+      </Grantee>
+      <Permission>READ</Permission>
+    </Grant>
+  </AccessControlList>
+</AccessControlPolicy>"#;
+        let acl: AccessControl = decode(body, &[]).unwrap();
+        assert_eq!(acl.owner.display_name, "gitai");
+        assert_eq!(
+            acl.permissions(&AclGrantee::Group(ALL_USERS.to_string())),
+            vec![AclPermission::Read]
+        );
+        assert_eq!(acl.grants[1].to_string(), "AllUsers=READ");
+
+        let mut again: AccessControl = decode(&acl.to_xml(), &[]).unwrap();
+        again.grants[0] = acl.grants[0].clone();
+        assert_eq!(again, acl);
+    }
+
+    #[test]
+    fn parse_grants() {
+        assert_eq!(
+            "email:a@example.com=read".parse::<AclGrant>().unwrap(),
+            AclGrant::new(
+                AclGrantee::Email(String::from("a@example.com")),
+                AclPermission::Read
+            )
+        );
+        assert_eq!(
+            "group:all=WRITE_ACP".parse::<AclGrant>().unwrap().grantee,
+            AclGrantee::Group(ALL_USERS.to_string())
+        );
+        assert!("id:=READ".parse::<AclGrant>().is_err());
+        assert!("user:abc=READ".parse::<AclGrant>().is_err());
+        assert!("id:abc=OWNER".parse::<AclGrant>().is_err());
+
+        let mut acl = AccessControl {
+            owner: AclOwner {
+                id: String::from("owner"),
+                ..Default::default()
+            },
+            grants: vec!["group:all=READ".parse().unwrap()],
+        };
+        acl.keep_owner();
+        assert_eq!(acl.permissions(&AclGrantee::user("owner")), vec![AclPermission::FullControl]);
+    }
+}
@@ -0,0 +1,231 @@
+// Copyright 2017 Gitai<i@gitai.me> All rights reserved.
+//
+// Permission is hereby granted, free of charge, to any person
+// obtaining a copy of this software and associated documentation
+// files (the "Software"), to deal in the Software without restriction,
+// including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall
+// be included in all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND,
+// EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES
+// OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.
+// IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR
+// ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF
+// CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION
+// WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+//! Asynchronous API for CTClient
+//!
+//! `AsyncCTClient` offers the operations of `CTClient`, `CTClientObject`,
+//! `CTClientEncryptionObject` and `CTClientIAM` returning futures, so a service
+//! running on an event loop (e.g. tokio) does not block its reactor thread.
+//!
+//! It is a thread-pool wrapper, not a non-blocking client: each request still runs
+//! the blocking `CTClient` on a thread of a `CpuPool`, which it holds until the
+//! response is read. At most `threads` requests are in flight, the other futures
+//! wait for a free thread. All of them share the client's credentials and
+//! connection pool.
+//!
+//! ```
+//! extern crate futures;
+//! extern crate ct_sdk;
+//!
+//! use futures::Future;
+//! use futures::future::join_all;
+//! use ct_sdk::ct::sdk::CTClient;
+//! use ct_sdk::ct::async_client::AsyncCTClient;
+//! use ct_sdk::ct::s3::object::*;
+//!
+//! fn main() {
+//!     let ct = AsyncCTClient::new(CTClient::default_client(), 16);
+//!     let heads = (0..1000).map(|i| {
+//!         let mut request = HeadObjectRequest::default();
+//!         request.bucket = "bucket".to_string();
+//!         request.key = format!("object-{}", i);
+//!         ct.head_object(request)
+//!     });
+//!     match join_all(heads).wait() {
+//!         Ok(out) => println!("{} objects", out.len()),
+//!         Err(err) => println!("{}", err),
+//!     }
+//! }
+//! ```
+
+use futures_cpupool::{Builder, CpuFuture, CpuPool};
+
+use aws_sdk_rust::aws::s3::bucket::*;
+use aws_sdk_rust::aws::s3::object::*;
+
+use ct::sdk::CTClient;
+use ct::error::CtError;
+use ct::object::{CTClientEncryptionObject, CTClientObject, PostObjectOutput, PostPolicy,
+                 PresignedObjectRequest};
+use ct::iam::*;
+
+/// The future returned by every `AsyncCTClient` operation.
+pub type CtFuture<T> = CpuFuture<T, CtError>;
+
+/// A `CTClient` whose operations return futures, run on a pool of threads.
+#[derive(Clone)]
+pub struct AsyncCTClient {
+    client: CTClient,
+    pool: CpuPool,
+}
+
+impl AsyncCTClient {
+    /// Run the requests of `client` on a pool of `threads` threads, the most requests
+    /// in flight at once.
+    pub fn new(client: CTClient, threads: usize) -> AsyncCTClient {
+        let pool = Builder::new()
+            .pool_size(threads)
+            .name_prefix("ct-client-")
+            .create();
+        AsyncCTClient::with_pool(client, pool)
+    }
+
+    /// Run the requests of `client` on an existing `pool`.
+    pub fn with_pool(client: CTClient, pool: CpuPool) -> AsyncCTClient {
+        AsyncCTClient {
+            client: client,
+            pool: pool,
+        }
+    }
+
+    /// The blocking client behind the futures.
+    pub fn client(&self) -> &CTClient {
+        &self.client
+    }
+
+    /// Run any blocking operation of `CTClient` on the pool.
+    ///
+    /// ```
+    /// let acl = ct.spawn(move |ct| ct.get_bucket_acl(&request).map_err(CtError::from));
+    /// ```
+    pub fn spawn<F, T>(&self, f: F) -> CtFuture<T>
+    where
+        F: FnOnce(&CTClient) -> Result<T, CtError> + Send + 'static,
+        T: Send + 'static,
+    {
+        let client = self.client.clone();
+        self.pool.spawn_fn(move || f(&client))
+    }
+
+    pub fn list_buckets(&self) -> CtFuture<ListBucketsOutput> {
+        self.spawn(|ct| ct.list_buckets().map_err(CtError::from))
+    }
+
+    pub fn create_bucket(&self, input: CreateBucketRequest) -> CtFuture<CreateBucketOutput> {
+        self.spawn(move |ct| ct.create_bucket(&input).map_err(CtError::from))
+    }
+
+    pub fn delete_bucket(&self, input: DeleteBucketRequest) -> CtFuture<()> {
+        self.spawn(move |ct| ct.delete_bucket(&input).map_err(CtError::from))
+    }
+
+    pub fn list_objects(&self, input: ListObjectsRequest) -> CtFuture<ListObjectsOutput> {
+        self.spawn(move |ct| ct.list_objects(&input).map_err(CtError::from))
+    }
+
+    pub fn head_object(&self, input: HeadObjectRequest) -> CtFuture<HeadObjectOutput> {
+        self.spawn(move |ct| ct.head_object(&input).map_err(CtError::from))
+    }
+
+    pub fn get_object(&self, input: GetObjectRequest) -> CtFuture<GetObjectOutput> {
+        self.spawn(move |ct| ct.get_object(&input, None).map_err(CtError::from))
+    }
+
+    /// Upload `body`, which replaces `input.body`.
+    pub fn put_object(
+        &self,
+        input: PutObjectRequest<'static>,
+        body: Vec<u8>,
+    ) -> CtFuture<PutObjectOutput> {
+        self.spawn(move |ct| {
+            let mut request: PutObjectRequest = input;
+            request.body = Some(&body);
+            ct.put_object(&request, None).map_err(CtError::from)
+        })
+    }
+
+    pub fn delete_object(&self, input: DeleteObjectRequest) -> CtFuture<DeleteObjectOutput> {
+        self.spawn(move |ct| ct.delete_object(&input, None).map_err(CtError::from))
+    }
+
+    pub fn presigned_object(&self, input: PresignedObjectRequest) -> CtFuture<String> {
+        self.spawn(move |ct| ct.presigned_object(&input))
+    }
+
+    pub fn post_object(&self, policy: PostPolicy) -> CtFuture<PostObjectOutput> {
+        self.spawn(move |ct| ct.post_object(&policy))
+    }
+
+    /// Encrypt and upload `body`, which replaces `input.body`.
+    pub fn put_object_securely(
+        &self,
+        input: PutObjectRequest<'static>,
+        body: Vec<u8>,
+    ) -> CtFuture<PutObjectOutput> {
+        self.spawn(move |ct| {
+            let mut request: PutObjectRequest = input;
+            request.body = Some(&body);
+            ct.put_object_securely(request, None)
+        })
+    }
+
+    pub fn get_object_securely(&self, input: GetObjectRequest) -> CtFuture<GetObjectOutput> {
+        self.spawn(move |ct| ct.get_object_securely(&input, None))
+    }
+
+    pub fn list_access_key(&self, input: ListAccessKeyRequest) -> CtFuture<ListAccessKeyOutput> {
+        self.spawn(move |ct| ct.list_access_key(&input))
+    }
+
+    pub fn create_access_key(&self) -> CtFuture<CreateAccessKeyOutput> {
+        self.spawn(|ct| ct.create_access_key())
+    }
+
+    pub fn delete_access_key(
+        &self,
+        input: DeleteAccessKeyRequest,
+    ) -> CtFuture<DeleteAccessKeyOutput> {
+        self.spawn(move |ct| ct.delete_access_key(&input))
+    }
+
+    pub fn update_access_key(
+        &self,
+        input: UpdateAccessKeyRequest,
+    ) -> CtFuture<UpdateAccessKeyOutput> {
+        self.spawn(move |ct| ct.update_access_key(&input))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::Future;
+    use futures::future::join_all;
+
+    use ct::sdk::CTClient;
+    use ct::error::CtError;
+    use ct::credentials::StaticProvider;
+    use super::AsyncCTClient;
+
+    #[test]
+    fn spawn_on_bounded_pool() {
+        let client = CTClient::new(StaticProvider::new("ak", "sk"), None, None);
+        let ct = AsyncCTClient::new(client, 2);
+        let futures = (0..16).map(|i| ct.spawn(move |_| Ok(i * 2)));
+        let out = join_all(futures).wait().unwrap();
+        assert_eq!(out, (0..16).map(|i| i * 2).collect::<Vec<_>>());
+
+        let err = ct.spawn(|_| -> Result<(), CtError> { Err(CtError::Authentication) });
+        match err.wait() {
+            Err(CtError::Authentication) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
@@ -20,12 +20,940 @@
 // WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 
 //! Additional API for Bucket Operations
+//!
+//! The configurations of a bucket, e.g. `?lifecycle`, are read and written as
+//! XML documents by `CTClientBucket`.
 pub use aws_sdk_rust::aws::s3::acl::CannedAcl::*;
 pub use aws_sdk_rust::aws::s3::bucket::*;
 
+use std::fmt::{Display, Error, Formatter};
+
+use md5::{Digest, Md5};
+use rustc_serialize::{Decodable, Decoder};
+use rustc_serialize::base64::{ToBase64, STANDARD};
+use chrono::NaiveDate;
+use xml::escape::escape_str_pcdata;
+
+use aws_sdk_rust::aws::common::signature::SignedRequest;
+
+use ct::sdk::CTClient;
+use ct::error::CtError;
+use ct::decode::{decode, Element, FromXml, XmlError};
+use ct::policy::{BucketPolicy, RefererConfiguration};
+
+pub type BucketName = String;
+
+/// Append `<name>value</name>` to `xml`.
+pub(crate) fn push_element<V: Display>(xml: &mut String, name: &str, value: V) {
+    xml.push_str(&format!(
+        "<{}>{}</{}>",
+        name,
+        escape_str_pcdata(&value.to_string()),
+        name
+    ));
+}
+
+/// Sign and send a request to the `subresource` configuration of `bucket`, or of
+/// its object `key` when not empty, returning the body of a successful response.
+///
+/// `payload` is the document sent and its content type.
+pub(crate) fn subresource_call(
+    ct: &CTClient,
+    method: &str,
+    bucket: &str,
+    key: &str,
+    subresource: &str,
+    payload: Option<(&[u8], &str)>,
+) -> Result<String, CtError> {
+    let path = format!("/{}", key);
+    let mut request = SignedRequest::new(method, "s3", ct.region(), bucket, &path, ct.endpoint());
+    request.set_hostname(Some(ct.hostname(Some(bucket))));
+    request.add_param(subresource, "");
+
+    if let Some((payload, content_type)) = payload {
+        let mut sh = Md5::default();
+        sh.consume(payload);
+        request.update_header("Content-MD5", &sh.hash().to_base64(STANDARD));
+        request.update_header("Content-Type", content_type);
+        request.set_payload(Some(payload));
+    }
+
+    let result = try!(ct.sign_and_dispatch(&mut request, true));
+    match result.status {
+        200 | 204 => Ok(result.body),
+        status => Err(CtError::from_response(status, &result.body)),
+    }
+}
+
+/// Status of a lifecycle rule
+#[derive(Debug, Clone, Copy, PartialEq, RustcDecodable, RustcEncodable)]
+pub enum LifecycleStatus {
+    Enabled,
+    Disabled,
+}
+
+impl Display for LifecycleStatus {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match self {
+            &LifecycleStatus::Enabled => write!(f, "Enabled"),
+            &LifecycleStatus::Disabled => write!(f, "Disabled"),
+        }
+    }
+}
+
+impl Default for LifecycleStatus {
+    fn default() -> Self {
+        LifecycleStatus::Enabled
+    }
+}
+
+impl FromXml for LifecycleStatus {
+    fn from_xml(element: &Element, path: &str) -> Result<LifecycleStatus, XmlError> {
+        match element.text.trim() {
+            "Enabled" => Ok(LifecycleStatus::Enabled),
+            "Disabled" => Ok(LifecycleStatus::Disabled),
+            text => Err(XmlError::new(path, &format!("invalid status {:?}", text))),
+        }
+    }
+}
+
+/// Expire the objects some days after their creation, or at a date
+#[derive(Debug, Default, Clone, PartialEq, RustcDecodable, RustcEncodable)]
+pub struct LifecycleExpiration {
+    pub days: Option<u32>,
+    /// Midnight UTC, e.g. `2018-01-01` or `2018-01-01T00:00:00.000Z`
+    pub date: Option<String>,
+}
+
+xml_struct! {
+    LifecycleExpiration {
+        "Days" => days,
+        "Date" => date,
+    }
+}
+
+/// Abort the multipart uploads left incomplete
+#[derive(Debug, Default, Clone, PartialEq, RustcDecodable, RustcEncodable)]
+pub struct AbortIncompleteMultipartUpload {
+    pub days_after_initiation: u32,
+}
+
+xml_struct! {
+    AbortIncompleteMultipartUpload {
+        "DaysAfterInitiation" => days_after_initiation,
+    }
+}
+
+/// A rule applied to the objects whose key starts with `prefix`
+#[derive(Debug, Default, Clone, PartialEq, RustcDecodable, RustcEncodable)]
+pub struct LifecycleRule {
+    pub id: String,
+    pub prefix: String,
+    pub status: LifecycleStatus,
+    pub expiration: Option<LifecycleExpiration>,
+    pub abort_incomplete_multipart_upload: Option<AbortIncompleteMultipartUpload>,
+}
+
+xml_struct! {
+    LifecycleRule {
+        "ID" => id,
+        "Prefix" => prefix,
+        "Status" => status,
+        "Expiration" => expiration,
+        "AbortIncompleteMultipartUpload" => abort_incomplete_multipart_upload,
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, RustcDecodable, RustcEncodable)]
+pub struct LifecycleConfiguration {
+    pub rules: Vec<LifecycleRule>,
+}
+
+xml_struct! {
+    LifecycleConfiguration {
+        "Rule" => [rules],
+    }
+}
+
+/// Expand a date to the midnight timestamp expected by OOS.
+fn lifecycle_date(date: &str) -> Result<String, String> {
+    let (day, time) = match date.len() > 10 && date.is_char_boundary(10) {
+        true => date.split_at(10),
+        false => (date, ""),
+    };
+    if NaiveDate::parse_from_str(day, "%Y-%m-%d").is_err() {
+        return Err(format!("invalid date {:?}, expected YYYY-MM-DD", date));
+    }
+    match time {
+        "" | "T00:00:00Z" | "T00:00:00.000Z" => Ok(format!("{}T00:00:00.000Z", day)),
+        _ => Err(format!("date {:?} must be midnight UTC", date)),
+    }
+}
+
+impl LifecycleConfiguration {
+    /// Check the rules before sending them.
+    pub fn validate(&self) -> Result<(), CtError> {
+        if self.rules.is_empty() || self.rules.len() > 1000 {
+            return Err(CtError::Config(String::from("1 to 1000 lifecycle rules expected")));
+        }
+        for (index, rule) in self.rules.iter().enumerate() {
+            let name = match rule.id.is_empty() {
+                true => format!("rule {}", index),
+                false => format!("rule {:?}", rule.id),
+            };
+            let invalid = |message: &str| Err(CtError::Config(format!("{}: {}", name, message)));
+
+            if rule.id.len() > 255 {
+                return invalid("ID longer than 255 characters");
+            }
+            if !rule.id.is_empty() && self.rules[..index].iter().any(|r| r.id == rule.id) {
+                return invalid("duplicate ID");
+            }
+            if rule.expiration.is_none() && rule.abort_incomplete_multipart_upload.is_none() {
+                return invalid("no expiration nor multipart upload cleanup");
+            }
+            if let Some(ref expiration) = rule.expiration {
+                match (expiration.days, expiration.date.as_ref()) {
+                    (Some(0), None) => return invalid("expiration days must be positive"),
+                    (Some(_), None) => {}
+                    (None, Some(date)) => if let Err(err) = lifecycle_date(date) {
+                        return invalid(err.as_str());
+                    },
+                    _ => return invalid("expiration needs either days or a date"),
+                }
+            }
+            if let Some(ref abort) = rule.abort_incomplete_multipart_upload {
+                if abort.days_after_initiation == 0 {
+                    return invalid("days after initiation must be positive");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The `LifecycleConfiguration` document
+    pub fn to_xml(&self) -> String {
+        let mut xml = String::from("<LifecycleConfiguration>");
+        for rule in &self.rules {
+            xml.push_str("<Rule>");
+            if !rule.id.is_empty() {
+                push_element(&mut xml, "ID", &rule.id);
+            }
+            push_element(&mut xml, "Prefix", &rule.prefix);
+            push_element(&mut xml, "Status", rule.status);
+            if let Some(ref expiration) = rule.expiration {
+                xml.push_str("<Expiration>");
+                if let Some(days) = expiration.days {
+                    push_element(&mut xml, "Days", days);
+                }
+                if let Some(ref date) = expiration.date {
+                    let date = lifecycle_date(date).unwrap_or(date.clone());
+                    push_element(&mut xml, "Date", date);
+                }
+                xml.push_str("</Expiration>");
+            }
+            if let Some(ref abort) = rule.abort_incomplete_multipart_upload {
+                xml.push_str("<AbortIncompleteMultipartUpload>");
+                push_element(&mut xml, "DaysAfterInitiation", abort.days_after_initiation);
+                xml.push_str("</AbortIncompleteMultipartUpload>");
+            }
+            xml.push_str("</Rule>");
+        }
+        xml.push_str("</LifecycleConfiguration>");
+        xml
+    }
+}
+
+/// Methods allowed by a CORS rule
+pub const CORS_METHODS: [&'static str; 5] = ["GET", "PUT", "POST", "DELETE", "HEAD"];
+
+/// Cross-origin requests allowed from a browser
+///
+/// In JSON, `allowed_headers` and `expose_headers` may be left out.
+#[derive(Debug, Default, Clone, PartialEq, RustcEncodable)]
+pub struct CorsRule {
+    pub id: Option<String>,
+    /// e.g. `https://www.example.com` or `*`, with at most one `*`
+    pub allowed_origins: Vec<String>,
+    /// Some of `CORS_METHODS`
+    pub allowed_methods: Vec<String>,
+    /// Headers of the preflight `Access-Control-Request-Headers`
+    pub allowed_headers: Vec<String>,
+    /// Response headers readable by the page, e.g. `ETag`
+    pub expose_headers: Vec<String>,
+    /// Cache duration of the preflight response
+    pub max_age_seconds: Option<u32>,
+}
+
+xml_struct! {
+    CorsRule {
+        "ID" => id,
+        "AllowedOrigin" => [allowed_origins],
+        "AllowedMethod" => [allowed_methods],
+        "AllowedHeader" => [allowed_headers],
+        "ExposeHeader" => [expose_headers],
+        "MaxAgeSeconds" => max_age_seconds,
+    }
+}
+
+impl Decodable for CorsRule {
+    fn decode<D: Decoder>(d: &mut D) -> Result<CorsRule, D::Error> {
+        d.read_struct("CorsRule", 6, |d| {
+            let headers: Option<Vec<String>> =
+                try!(d.read_struct_field("allowed_headers", 3, Decodable::decode));
+            let expose: Option<Vec<String>> =
+                try!(d.read_struct_field("expose_headers", 4, Decodable::decode));
+            Ok(CorsRule {
+                id: try!(d.read_struct_field("id", 0, Decodable::decode)),
+                allowed_origins: try!(d.read_struct_field("allowed_origins", 1, Decodable::decode)),
+                allowed_methods: try!(d.read_struct_field("allowed_methods", 2, Decodable::decode)),
+                allowed_headers: headers.unwrap_or_default(),
+                expose_headers: expose.unwrap_or_default(),
+                max_age_seconds: try!(d.read_struct_field("max_age_seconds", 5, Decodable::decode)),
+            })
+        })
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, RustcDecodable, RustcEncodable)]
+pub struct CorsConfiguration {
+    pub rules: Vec<CorsRule>,
+}
+
+xml_struct! {
+    CorsConfiguration {
+        "CORSRule" => [rules],
+    }
+}
+
+impl CorsConfiguration {
+    /// Check the rules before sending them.
+    pub fn validate(&self) -> Result<(), CtError> {
+        if self.rules.is_empty() || self.rules.len() > 100 {
+            return Err(CtError::Config(String::from("1 to 100 CORS rules expected")));
+        }
+        for (index, rule) in self.rules.iter().enumerate() {
+            let name = match rule.id {
+                Some(ref id) => format!("rule {:?}", id),
+                None => format!("rule {}", index),
+            };
+            let invalid = |message: String| Err(CtError::Config(format!("{}: {}", name, message)));
+
+            if rule.allowed_origins.is_empty() {
+                return invalid(String::from("no allowed origin"));
+            }
+            if rule.allowed_methods.is_empty() {
+                return invalid(String::from("no allowed method"));
+            }
+            let wildcards = rule.allowed_origins.iter().chain(rule.allowed_headers.iter());
+            for value in wildcards {
+                if value.matches('*').count() > 1 {
+                    return invalid(format!("{:?} has more than one *", value));
+                }
+            }
+            for method in &rule.allowed_methods {
+                if !CORS_METHODS.iter().any(|m| *m == method.as_str()) {
+                    return invalid(format!("unknown method {:?}", method));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The `CORSConfiguration` document
+    pub fn to_xml(&self) -> String {
+        let mut xml = String::from("<CORSConfiguration>");
+        for rule in &self.rules {
+            xml.push_str("<CORSRule>");
+            if let Some(ref id) = rule.id {
+                push_element(&mut xml, "ID", id);
+            }
+            for origin in &rule.allowed_origins {
+                push_element(&mut xml, "AllowedOrigin", origin);
+            }
+            for method in &rule.allowed_methods {
+                push_element(&mut xml, "AllowedMethod", method);
+            }
+            for header in &rule.allowed_headers {
+                push_element(&mut xml, "AllowedHeader", header);
+            }
+            for header in &rule.expose_headers {
+                push_element(&mut xml, "ExposeHeader", header);
+            }
+            if let Some(max_age) = rule.max_age_seconds {
+                push_element(&mut xml, "MaxAgeSeconds", max_age);
+            }
+            xml.push_str("</CORSRule>");
+        }
+        xml.push_str("</CORSConfiguration>");
+        xml
+    }
+}
+
+/// Redirect every request of the website to another host
+#[derive(Debug, Default, Clone, PartialEq, RustcDecodable, RustcEncodable)]
+pub struct RedirectAllRequestsTo {
+    pub host_name: String,
+    /// `http` or `https`, the protocol of the request by default
+    pub protocol: Option<String>,
+}
+
+xml_struct! {
+    RedirectAllRequestsTo {
+        "HostName" => host_name,
+        "Protocol" => protocol,
+    }
+}
+
+/// When a routing rule applies, every request when both are `None`
+#[derive(Debug, Default, Clone, PartialEq, RustcDecodable, RustcEncodable)]
+pub struct RoutingCondition {
+    pub key_prefix_equals: Option<String>,
+    pub http_error_code_returned_equals: Option<u16>,
+}
+
+xml_struct! {
+    RoutingCondition {
+        "KeyPrefixEquals" => key_prefix_equals,
+        "HttpErrorCodeReturnedEquals" => http_error_code_returned_equals,
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, RustcDecodable, RustcEncodable)]
+pub struct RoutingRedirect {
+    pub protocol: Option<String>,
+    pub host_name: Option<String>,
+    pub replace_key_prefix_with: Option<String>,
+    pub replace_key_with: Option<String>,
+    /// 301 by default
+    pub http_redirect_code: Option<u16>,
+}
+
+xml_struct! {
+    RoutingRedirect {
+        "Protocol" => protocol,
+        "HostName" => host_name,
+        "ReplaceKeyPrefixWith" => replace_key_prefix_with,
+        "ReplaceKeyWith" => replace_key_with,
+        "HttpRedirectCode" => http_redirect_code,
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, RustcDecodable, RustcEncodable)]
+pub struct RoutingRule {
+    pub condition: Option<RoutingCondition>,
+    pub redirect: RoutingRedirect,
+}
+
+xml_struct! {
+    RoutingRule {
+        "Condition" => condition,
+        "Redirect" => redirect,
+    }
+}
+
+/// Static website hosting of a bucket
+#[derive(Debug, Default, Clone, PartialEq, RustcDecodable, RustcEncodable)]
+pub struct WebsiteConfiguration {
+    /// Suffix appended to the requests of a directory, e.g. `index.html`
+    pub index_document: Option<String>,
+    /// Key returned on a 4XX error, e.g. `404.html`
+    pub error_document: Option<String>,
+    /// Exclusive with the other fields
+    pub redirect_all_requests_to: Option<RedirectAllRequestsTo>,
+    pub routing_rules: Vec<RoutingRule>,
+}
+
+impl FromXml for WebsiteConfiguration {
+    fn from_xml(element: &Element, path: &str) -> Result<WebsiteConfiguration, XmlError> {
+        let mut website = WebsiteConfiguration::default();
+        for child in &element.children {
+            let path = format!("{}/{}", path, child.name);
+            let text = |name: &str| child.child(name).map(|value| value.text.trim().to_string());
+            match child.name.as_str() {
+                "IndexDocument" => website.index_document = text("Suffix"),
+                "ErrorDocument" => website.error_document = text("Key"),
+                "RedirectAllRequestsTo" => {
+                    website.redirect_all_requests_to = Some(try!(FromXml::from_xml(child, &path)))
+                }
+                "RoutingRules" => website.routing_rules = try!(FromXml::from_xml(child, &path)),
+                _ => {}
+            }
+        }
+        Ok(website)
+    }
+}
+
+fn valid_redirect_code(code: Option<u16>, min: u16, max: u16) -> bool {
+    code.map(|code| code >= min && code <= max).unwrap_or(true)
+}
+
+impl WebsiteConfiguration {
+    /// Serve `index` for directories and `error` on errors.
+    pub fn new(index: &str, error: Option<&str>) -> WebsiteConfiguration {
+        WebsiteConfiguration {
+            index_document: Some(index.to_string()),
+            error_document: error.map(|error| error.to_string()),
+            ..Default::default()
+        }
+    }
+
+    /// Check the configuration before sending it.
+    pub fn validate(&self) -> Result<(), CtError> {
+        let invalid = |message: &str| Err(CtError::Config(format!("website: {}", message)));
+
+        if let Some(ref redirect) = self.redirect_all_requests_to {
+            if self.index_document.is_some() || self.error_document.is_some() ||
+                !self.routing_rules.is_empty()
+            {
+                return invalid("redirect of all requests excludes the other settings");
+            }
+            if redirect.host_name.is_empty() {
+                return invalid("redirect without host name");
+            }
+            return Ok(());
+        }
+        match self.index_document {
+            Some(ref suffix) if !suffix.is_empty() && !suffix.contains('/') => {}
+            Some(_) => return invalid("index document must be a name without /"),
+            None => return invalid("index document expected"),
+        }
+        for (index, rule) in self.routing_rules.iter().enumerate() {
+            let redirect = &rule.redirect;
+            if redirect.replace_key_prefix_with.is_some() && redirect.replace_key_with.is_some() {
+                return invalid(&format!(
+                    "routing rule {}: replace either the key or its prefix",
+                    index
+                ));
+            }
+            if !valid_redirect_code(redirect.http_redirect_code, 300, 399) {
+                return invalid(&format!("routing rule {}: redirect code must be 3XX", index));
+            }
+            if let Some(ref condition) = rule.condition {
+                if !valid_redirect_code(condition.http_error_code_returned_equals, 400, 599) {
+                    return invalid(&format!(
+                        "routing rule {}: error code must be 4XX or 5XX",
+                        index
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The `WebsiteConfiguration` document
+    pub fn to_xml(&self) -> String {
+        let mut xml = String::from("<WebsiteConfiguration>");
+        if let Some(ref redirect) = self.redirect_all_requests_to {
+            xml.push_str("<RedirectAllRequestsTo>");
+            push_element(&mut xml, "HostName", &redirect.host_name);
+            if let Some(ref protocol) = redirect.protocol {
+                push_element(&mut xml, "Protocol", protocol);
+            }
+            xml.push_str("</RedirectAllRequestsTo>");
+        }
+        if let Some(ref suffix) = self.index_document {
+            xml.push_str("<IndexDocument>");
+            push_element(&mut xml, "Suffix", suffix);
+            xml.push_str("</IndexDocument>");
+        }
+        if let Some(ref key) = self.error_document {
+            xml.push_str("<ErrorDocument>");
+            push_element(&mut xml, "Key", key);
+            xml.push_str("</ErrorDocument>");
+        }
+        if !self.routing_rules.is_empty() {
+            xml.push_str("<RoutingRules>");
+            for rule in &self.routing_rules {
+                xml.push_str("<RoutingRule>");
+                if let Some(ref condition) = rule.condition {
+                    xml.push_str("<Condition>");
+                    if let Some(ref prefix) = condition.key_prefix_equals {
+                        push_element(&mut xml, "KeyPrefixEquals", prefix);
+                    }
+                    if let Some(code) = condition.http_error_code_returned_equals {
+                        push_element(&mut xml, "HttpErrorCodeReturnedEquals", code);
+                    }
+                    xml.push_str("</Condition>");
+                }
+                let redirect = &rule.redirect;
+                xml.push_str("<Redirect>");
+                if let Some(ref protocol) = redirect.protocol {
+                    push_element(&mut xml, "Protocol", protocol);
+                }
+                if let Some(ref host_name) = redirect.host_name {
+                    push_element(&mut xml, "HostName", host_name);
+                }
+                if let Some(ref prefix) = redirect.replace_key_prefix_with {
+                    push_element(&mut xml, "ReplaceKeyPrefixWith", prefix);
+                }
+                if let Some(ref key) = redirect.replace_key_with {
+                    push_element(&mut xml, "ReplaceKeyWith", key);
+                }
+                if let Some(code) = redirect.http_redirect_code {
+                    push_element(&mut xml, "HttpRedirectCode", code);
+                }
+                xml.push_str("</Redirect></RoutingRule>");
+            }
+            xml.push_str("</RoutingRules>");
+        }
+        xml.push_str("</WebsiteConfiguration>");
+        xml
+    }
+}
+
+/// Where the access logs of a bucket are written
+#[derive(Debug, Default, Clone, PartialEq, RustcDecodable, RustcEncodable)]
+pub struct LoggingEnabled {
+    pub target_bucket: BucketName,
+    /// Prefix of the log objects, e.g. `logs/photos/`
+    pub target_prefix: String,
+}
+
+xml_struct! {
+    LoggingEnabled {
+        "TargetBucket" => target_bucket,
+        "TargetPrefix" => target_prefix,
+    }
+}
+
+/// Access logging of a bucket, disabled when `logging_enabled` is `None`
+#[derive(Debug, Default, Clone, PartialEq, RustcDecodable, RustcEncodable)]
+pub struct BucketLoggingStatus {
+    pub logging_enabled: Option<LoggingEnabled>,
+}
+
+xml_struct! {
+    BucketLoggingStatus {
+        "LoggingEnabled" => logging_enabled,
+    }
+}
+
+impl BucketLoggingStatus {
+    /// Write the logs to `target_bucket` under `target_prefix`.
+    pub fn enabled(target_bucket: &str, target_prefix: &str) -> BucketLoggingStatus {
+        BucketLoggingStatus {
+            logging_enabled: Some(LoggingEnabled {
+                target_bucket: target_bucket.to_string(),
+                target_prefix: target_prefix.to_string(),
+            }),
+        }
+    }
+
+    /// The `BucketLoggingStatus` document
+    pub fn to_xml(&self) -> String {
+        let mut xml = String::from(
+            "<BucketLoggingStatus xmlns=\"http://doc.s3.amazonaws.com/2006-03-01\">",
+        );
+        if let Some(ref logging) = self.logging_enabled {
+            xml.push_str("<LoggingEnabled>");
+            push_element(&mut xml, "TargetBucket", &logging.target_bucket);
+            push_element(&mut xml, "TargetPrefix", &logging.target_prefix);
+            xml.push_str("</LoggingEnabled>");
+        }
+        xml.push_str("</BucketLoggingStatus>");
+        xml
+    }
+}
+
+/// Versioning state of a bucket, once enabled it can only be suspended
+#[derive(Debug, Clone, Copy, PartialEq, RustcDecodable, RustcEncodable)]
+pub enum VersioningStatus {
+    Enabled,
+    Suspended,
+}
+
+impl Display for VersioningStatus {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match self {
+            &VersioningStatus::Enabled => write!(f, "Enabled"),
+            &VersioningStatus::Suspended => write!(f, "Suspended"),
+        }
+    }
+}
+
+impl FromXml for VersioningStatus {
+    fn from_xml(element: &Element, path: &str) -> Result<VersioningStatus, XmlError> {
+        match element.text.trim() {
+            "Enabled" => Ok(VersioningStatus::Enabled),
+            "Suspended" => Ok(VersioningStatus::Suspended),
+            text => Err(XmlError::new(path, &format!("invalid status {:?}", text))),
+        }
+    }
+}
+
+/// Versioning of a bucket, `status` is `None` if it has never been enabled
+#[derive(Debug, Default, Clone, PartialEq, RustcDecodable, RustcEncodable)]
+pub struct VersioningConfiguration {
+    pub status: Option<VersioningStatus>,
+}
+
+xml_struct! {
+    VersioningConfiguration {
+        "Status" => status,
+    }
+}
+
+impl VersioningConfiguration {
+    /// The `VersioningConfiguration` document
+    pub fn to_xml(&self) -> String {
+        let mut xml = String::from(
+            "<VersioningConfiguration xmlns=\"http://s3.amazonaws.com/doc/2006-03-01/\">",
+        );
+        if let Some(status) = self.status {
+            push_element(&mut xml, "Status", status);
+        }
+        xml.push_str("</VersioningConfiguration>");
+        xml
+    }
+}
+
+/// Additional bucket configurations for CTClient.
+pub trait CTClientBucket {
+    /// Lifecycle rules of a bucket, `NoSuchLifecycleConfiguration` when none is set.
+    fn get_bucket_lifecycle(&self, bucket: &str) -> Result<LifecycleConfiguration, CtError>;
+    /// Replace the lifecycle rules of a bucket, after `LifecycleConfiguration::validate`.
+    fn put_bucket_lifecycle(
+        &self,
+        bucket: &str,
+        lifecycle: &LifecycleConfiguration,
+    ) -> Result<(), CtError>;
+    /// Remove all the lifecycle rules of a bucket.
+    fn delete_bucket_lifecycle(&self, bucket: &str) -> Result<(), CtError>;
+    /// Policy of a bucket, `NoSuchBucketPolicy` when none is set.
+    fn get_bucket_policy(&self, bucket: &str) -> Result<BucketPolicy, CtError>;
+    /// Replace the policy of a bucket, after `BucketPolicy::validate`.
+    fn put_bucket_policy(&self, bucket: &str, policy: &BucketPolicy) -> Result<(), CtError>;
+    /// Remove the policy of a bucket.
+    fn delete_bucket_policy(&self, bucket: &str) -> Result<(), CtError>;
+    /// CORS rules of a bucket, `NoSuchCORSConfiguration` when none is set.
+    fn get_bucket_cors(&self, bucket: &str) -> Result<CorsConfiguration, CtError>;
+    /// Replace the CORS rules of a bucket, after `CorsConfiguration::validate`.
+    fn put_bucket_cors(&self, bucket: &str, cors: &CorsConfiguration) -> Result<(), CtError>;
+    /// Remove all the CORS rules of a bucket.
+    fn delete_bucket_cors(&self, bucket: &str) -> Result<(), CtError>;
+    /// Website configuration of a bucket, `NoSuchWebsiteConfiguration` when none is set.
+    fn get_bucket_website(&self, bucket: &str) -> Result<WebsiteConfiguration, CtError>;
+    /// Host a static website, after `WebsiteConfiguration::validate`.
+    fn put_bucket_website(
+        &self,
+        bucket: &str,
+        website: &WebsiteConfiguration,
+    ) -> Result<(), CtError>;
+    /// Stop hosting the website of a bucket.
+    fn delete_bucket_website(&self, bucket: &str) -> Result<(), CtError>;
+    /// Access logging of a bucket.
+    fn get_bucket_logging(&self, bucket: &str) -> Result<BucketLoggingStatus, CtError>;
+    /// Enable the access logs of a bucket, or disable them with the default status.
+    ///
+    /// The target bucket must grant `WRITE` and `READ_ACP` to the log delivery group.
+    fn put_bucket_logging(
+        &self,
+        bucket: &str,
+        logging: &BucketLoggingStatus,
+    ) -> Result<(), CtError>;
+    /// Hotlink protection of a bucket, read from its policy.
+    fn get_bucket_referer(&self, bucket: &str) -> Result<RefererConfiguration, CtError>;
+    /// Replace the hotlink protection in the policy of a bucket, keeping its other statements.
+    fn put_bucket_referer(
+        &self,
+        bucket: &str,
+        referer: &RefererConfiguration,
+    ) -> Result<(), CtError>;
+    /// Versioning of a bucket.
+    fn get_bucket_versioning(&self, bucket: &str) -> Result<VersioningConfiguration, CtError>;
+    /// Enable or suspend the versioning of a bucket, existing versions are kept.
+    fn put_bucket_versioning(&self, bucket: &str, status: VersioningStatus) -> Result<(), CtError>;
+}
+
+impl CTClientBucket for CTClient {
+    fn get_bucket_lifecycle(&self, bucket: &str) -> Result<LifecycleConfiguration, CtError> {
+        let body = try!(subresource_call(self, "GET", bucket, "", "lifecycle", None));
+        Ok(try!(decode(&body, &[])))
+    }
+
+    fn put_bucket_lifecycle(
+        &self,
+        bucket: &str,
+        lifecycle: &LifecycleConfiguration,
+    ) -> Result<(), CtError> {
+        try!(lifecycle.validate());
+        let payload = lifecycle.to_xml().into_bytes();
+        try!(subresource_call(
+            self,
+            "PUT",
+            bucket,
+            "",
+            "lifecycle",
+            Some((&payload, "application/xml"))
+        ));
+        Ok(())
+    }
+
+    fn delete_bucket_lifecycle(&self, bucket: &str) -> Result<(), CtError> {
+        try!(subresource_call(self, "DELETE", bucket, "", "lifecycle", None));
+        Ok(())
+    }
+
+    fn get_bucket_policy(&self, bucket: &str) -> Result<BucketPolicy, CtError> {
+        let body = try!(subresource_call(self, "GET", bucket, "", "policy", None));
+        BucketPolicy::from_json(&body)
+    }
+
+    fn put_bucket_policy(&self, bucket: &str, policy: &BucketPolicy) -> Result<(), CtError> {
+        try!(policy.validate(bucket));
+        let payload = policy.to_json().to_string().into_bytes();
+        try!(subresource_call(
+            self,
+            "PUT",
+            bucket,
+            "",
+            "policy",
+            Some((&payload, "application/json"))
+        ));
+        Ok(())
+    }
+
+    fn delete_bucket_policy(&self, bucket: &str) -> Result<(), CtError> {
+        try!(subresource_call(self, "DELETE", bucket, "", "policy", None));
+        Ok(())
+    }
+
+    fn get_bucket_cors(&self, bucket: &str) -> Result<CorsConfiguration, CtError> {
+        let body = try!(subresource_call(self, "GET", bucket, "", "cors", None));
+        Ok(try!(decode(&body, &[])))
+    }
+
+    fn put_bucket_cors(&self, bucket: &str, cors: &CorsConfiguration) -> Result<(), CtError> {
+        try!(cors.validate());
+        let payload = cors.to_xml().into_bytes();
+        try!(subresource_call(
+            self,
+            "PUT",
+            bucket,
+            "",
+            "cors",
+            Some((&payload, "application/xml"))
+        ));
+        Ok(())
+    }
+
+    fn delete_bucket_cors(&self, bucket: &str) -> Result<(), CtError> {
+        try!(subresource_call(self, "DELETE", bucket, "", "cors", None));
+        Ok(())
+    }
+
+    fn get_bucket_website(&self, bucket: &str) -> Result<WebsiteConfiguration, CtError> {
+        let body = try!(subresource_call(self, "GET", bucket, "", "website", None));
+        Ok(try!(decode(&body, &[])))
+    }
+
+    fn put_bucket_website(
+        &self,
+        bucket: &str,
+        website: &WebsiteConfiguration,
+    ) -> Result<(), CtError> {
+        try!(website.validate());
+        let payload = website.to_xml().into_bytes();
+        try!(subresource_call(
+            self,
+            "PUT",
+            bucket,
+            "",
+            "website",
+            Some((&payload, "application/xml"))
+        ));
+        Ok(())
+    }
+
+    fn delete_bucket_website(&self, bucket: &str) -> Result<(), CtError> {
+        try!(subresource_call(self, "DELETE", bucket, "", "website", None));
+        Ok(())
+    }
+
+    fn get_bucket_logging(&self, bucket: &str) -> Result<BucketLoggingStatus, CtError> {
+        let body = try!(subresource_call(self, "GET", bucket, "", "logging", None));
+        Ok(try!(decode(&body, &[])))
+    }
+
+    fn put_bucket_logging(
+        &self,
+        bucket: &str,
+        logging: &BucketLoggingStatus,
+    ) -> Result<(), CtError> {
+        if let Some(ref enabled) = logging.logging_enabled {
+            if enabled.target_bucket.is_empty() {
+                return Err(CtError::Config(String::from("logging without target bucket")));
+            }
+        }
+        let payload = logging.to_xml().into_bytes();
+        try!(subresource_call(
+            self,
+            "PUT",
+            bucket,
+            "",
+            "logging",
+            Some((&payload, "application/xml"))
+        ));
+        Ok(())
+    }
+
+    fn get_bucket_referer(&self, bucket: &str) -> Result<RefererConfiguration, CtError> {
+        match self.get_bucket_policy(bucket) {
+            Ok(policy) => Ok(RefererConfiguration::from_policy(&policy)),
+            Err(ref err) if err.code() == Some("NoSuchBucketPolicy") => {
+                Ok(RefererConfiguration::default())
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    fn put_bucket_referer(
+        &self,
+        bucket: &str,
+        referer: &RefererConfiguration,
+    ) -> Result<(), CtError> {
+        try!(referer.validate());
+        let mut policy = match self.get_bucket_policy(bucket) {
+            Ok(policy) => policy,
+            Err(ref err) if err.code() == Some("NoSuchBucketPolicy") => BucketPolicy::new(vec![]),
+            Err(err) => return Err(err),
+        };
+        policy.set_referer(bucket, referer);
+        match policy.statements.is_empty() {
+            true => self.delete_bucket_policy(bucket),
+            false => self.put_bucket_policy(bucket, &policy),
+        }
+    }
+
+    fn get_bucket_versioning(&self, bucket: &str) -> Result<VersioningConfiguration, CtError> {
+        let body = try!(subresource_call(self, "GET", bucket, "", "versioning", None));
+        Ok(try!(decode(&body, &[])))
+    }
+
+    fn put_bucket_versioning(&self, bucket: &str, status: VersioningStatus) -> Result<(), CtError> {
+        let versioning = VersioningConfiguration {
+            status: Some(status),
+        };
+        let payload = versioning.to_xml().into_bytes();
+        try!(subresource_call(
+            self,
+            "PUT",
+            bucket,
+            "",
+            "versioning",
+            Some((&payload, "application/xml"))
+        ));
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::super::sdk::CTClient;
+    use super::super::acl::*;
+    use super::*;
+    use rustc_serialize::json;
 
     static BUCKET: &'static str = "gitai.test";
 
@@ -83,20 +1011,15 @@ mod tests {
             Err(err) => assert!(false, err),
         };
 
-        match s3.get_bucket_acl(&GetBucketAclRequest {
-            bucket: String::from(BUCKET),
-        }) {
+        match CTClient::default_client().get_bucket_grants(BUCKET) {
             Ok(acl) => {
-                for grant in acl.acl.grants {
-                    if grant.permission.eq("FULL_CONTROL") {
-                        assert!(true);
-                        return;
-                    }
-                }
-                assert!(
-                    false,
-                    format!("FULL_CONTROL not found in bucket {}`s permission", BUCKET)
-                )
+                let owner = AclGrantee::user(&acl.owner.id);
+                assert_eq!(acl.permissions(&owner), vec![AclPermission::FullControl]);
+                let everyone = AclGrantee::Group(ALL_USERS.to_string());
+                assert_eq!(
+                    acl.permissions(&everyone),
+                    vec![AclPermission::Read, AclPermission::Write]
+                );
             }
             Err(err) => assert!(false, err),
         };
@@ -121,4 +1044,162 @@ mod tests {
             Err(_) => assert!(true),
         };
     }
+
+    #[test]
+    fn lifecycle_document() {
+        let body = r#"<?xml version="1.0" encoding="UTF-8"?>
+<LifecycleConfiguration xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+  <Rule>
+    <ID>logs</ID>
+    <Prefix>logs/</Prefix>
+    <Status>Enabled</Status>
+    <Expiration><Days>30</Days></Expiration>
+  </Rule>
+  <Rule>
+    <Prefix>tmp/</Prefix>
+    <Status>Disabled</Status>
+    <Expiration><Date>2018-01-01T00:00:00.000Z</Date></Expiration>
+    <AbortIncompleteMultipartUpload>
+      <DaysAfterInitiation>7</DaysAfterInitiation>
+    </AbortIncompleteMultipartUpload>
+  </Rule>
+</LifecycleConfiguration>"#;
+        let config: LifecycleConfiguration = decode(body, &[]).unwrap();
+        assert_eq!(config.rules.len(), 2);
+        assert_eq!(config.rules[0].expiration.as_ref().unwrap().days, Some(30));
+        assert_eq!(config.rules[1].status, LifecycleStatus::Disabled);
+        assert!(config.validate().is_ok());
+
+        let again: LifecycleConfiguration = decode(&config.to_xml(), &[]).unwrap();
+        assert_eq!(again, config);
+
+        let mut invalid = config.clone();
+        invalid.rules[1].id = String::from("logs");
+        assert!(invalid.validate().is_err());
+
+        let mut invalid = config.clone();
+        invalid.rules[0].expiration = Some(LifecycleExpiration {
+            days: Some(30),
+            date: Some(String::from("2018-01-01")),
+        });
+        assert!(invalid.validate().is_err());
+
+        let mut invalid = config;
+        invalid.rules[0].expiration = Some(LifecycleExpiration {
+            days: None,
+            date: Some(String::from("2018-01-01T12:00:00Z")),
+        });
+        assert!(invalid.validate().is_err());
+    }
+
+    #[test]
+    fn cors_document() {
+        let body = r#"<CORSConfiguration>
+  <CORSRule>
+    <AllowedOrigin>https://www.example.com</AllowedOrigin>
+    <AllowedOrigin>https://*.example.com</AllowedOrigin>
+    <AllowedMethod>PUT</AllowedMethod>
+    <AllowedMethod>POST</AllowedMethod>
+    <AllowedHeader>*</AllowedHeader>
+    <ExposeHeader>ETag</ExposeHeader>
+    <MaxAgeSeconds>3000</MaxAgeSeconds>
+  </CORSRule>
+</CORSConfiguration>"#;
+        let cors: CorsConfiguration = decode(body, &[]).unwrap();
+        let rule = &cors.rules[0];
+        assert_eq!(rule.allowed_origins.len(), 2);
+        assert_eq!(rule.allowed_methods, vec![String::from("PUT"), String::from("POST")]);
+        assert_eq!(rule.max_age_seconds, Some(3000));
+        assert!(cors.validate().is_ok());
+
+        let again: CorsConfiguration = decode(&cors.to_xml(), &[]).unwrap();
+        assert_eq!(again, cors);
+
+        let json = r#"{"rules": [{"allowed_origins": ["*"], "allowed_methods": ["GET"]}]}"#;
+        let minimal: CorsConfiguration = json::decode(json).unwrap();
+        assert!(minimal.rules[0].allowed_headers.is_empty());
+        assert_eq!(minimal.rules[0].max_age_seconds, None);
+        let again: CorsConfiguration = json::decode(&json::encode(&cors).unwrap()).unwrap();
+        assert_eq!(again, cors);
+
+        let mut invalid = cors.clone();
+        invalid.rules[0].allowed_methods.push(String::from("PATCH"));
+        assert!(invalid.validate().is_err());
+
+        let mut invalid = cors;
+        invalid.rules[0].allowed_origins = vec![String::from("https://*.*.com")];
+        assert!(invalid.validate().is_err());
+    }
+
+    #[test]
+    fn website_document() {
+        let body = r#"<WebsiteConfiguration>
+  <IndexDocument><Suffix>index.html</Suffix></IndexDocument>
+  <ErrorDocument><Key>404.html</Key></ErrorDocument>
+  <RoutingRules>
+    <RoutingRule>
+      <Condition><KeyPrefixEquals>docs/</KeyPrefixEquals></Condition>
+      <Redirect><ReplaceKeyPrefixWith>documents/</ReplaceKeyPrefixWith></Redirect>
+    </RoutingRule>
+  </RoutingRules>
+</WebsiteConfiguration>"#;
+        let website: WebsiteConfiguration = decode(body, &[]).unwrap();
+        assert_eq!(website.index_document, Some(String::from("index.html")));
+        assert_eq!(website.error_document, Some(String::from("404.html")));
+        assert_eq!(
+            website.routing_rules[0].redirect.replace_key_prefix_with,
+            Some(String::from("documents/"))
+        );
+        assert!(website.validate().is_ok());
+
+        let again: WebsiteConfiguration = decode(&website.to_xml(), &[]).unwrap();
+        assert_eq!(again, website);
+
+        let mut invalid = website.clone();
+        invalid.redirect_all_requests_to = Some(RedirectAllRequestsTo {
+            host_name: String::from("example.com"),
+            protocol: None,
+        });
+        assert!(invalid.validate().is_err());
+
+        let mut invalid = website;
+        invalid.routing_rules[0].redirect.http_redirect_code = Some(404);
+        assert!(invalid.validate().is_err());
+
+        assert!(WebsiteConfiguration::new("docs/index.html", None).validate().is_err());
+    }
+
+    #[test]
+    fn versioning_document() {
+        let versioning = VersioningConfiguration {
+            status: Some(VersioningStatus::Suspended),
+        };
+        let xml = versioning.to_xml();
+        assert!(xml.contains("<Status>Suspended</Status>"));
+        assert_eq!(decode::<VersioningConfiguration>(&xml, &[]).unwrap(), versioning);
+
+        let never = "<VersioningConfiguration xmlns=\"http://s3.amazonaws.com/doc/2006-03-01/\"/>";
+        assert_eq!(decode::<VersioningConfiguration>(never, &[]).unwrap().status, None);
+        assert!(decode::<VersioningConfiguration>(
+            "<VersioningConfiguration><Status>On</Status></VersioningConfiguration>",
+            &[]
+        ).is_err());
+    }
+
+    #[test]
+    fn logging_document() {
+        let logging = BucketLoggingStatus::enabled("logs", "photos/");
+        let xml = logging.to_xml();
+        assert!(xml.contains("<TargetBucket>logs</TargetBucket>"));
+        assert!(xml.contains("<TargetPrefix>photos/</TargetPrefix>"));
+        assert_eq!(decode::<BucketLoggingStatus>(&xml, &[]).unwrap(), logging);
+
+        let disabled = BucketLoggingStatus::default();
+        assert_eq!(
+            disabled.to_xml(),
+            "<BucketLoggingStatus xmlns=\"http://doc.s3.amazonaws.com/2006-03-01\">\
+             </BucketLoggingStatus>"
+        );
+        assert_eq!(decode::<BucketLoggingStatus>(&disabled.to_xml(), &[]).unwrap(), disabled);
+    }
 }
@@ -0,0 +1,628 @@
+// Copyright 2017 Gitai<i@gitai.me> All rights reserved.
+//
+// Permission is hereby granted, free of charge, to any person
+// obtaining a copy of this software and associated documentation
+// files (the "Software"), to deal in the Software without restriction,
+// including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall
+// be included in all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND,
+// EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES
+// OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.
+// IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR
+// ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF
+// CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION
+// WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+//! Credentials Providers for CTClient
+//!
+//! Besides the providers of aws-sdk-rust, `CTClient` accepts:
+//!
+//! * `StaticProvider`, a pair of keys kept in memory;
+//! * `CtProfileProvider`, a profile of the ct credentials file (`~/.ct/credentials`);
+//! * `ProcessProvider`, the JSON printed by an external command;
+//! * `CredentialsChain`, the first of several providers which succeeds. Its error
+//!   lists every source tried and why it failed;
+//! * `CachedProvider`, the credentials of another provider kept until they expire.
+//!
+//! ```
+//! let provider = CredentialsChain::new()
+//!     .with("static", StaticProvider::new("ak", "sk"))
+//!     .with("profile", CtProfileProvider::new())
+//!     .cached();
+//! let ct = CTClient::new(provider, None, None);
+//! ```
+
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+
+use chrono::{DateTime, Duration, UTC};
+use rustc_serialize::json::Json;
+
+use aws_sdk_rust::aws::common::credentials::{AwsCredentials, AwsCredentialsProvider,
+                                             CredentialsError, EnvironmentProvider,
+                                             IamProvider, ProfileProvider};
+
+/// Path of the credentials file, overridden by `CT_CREDENTIALS_FILE`.
+pub const CREDENTIALS_FILE: &'static str = ".ct/credentials";
+
+/// Credentials which never expire.
+fn long_lived(access_key_id: &str, secret_access_key: &str, token: Option<String>) -> AwsCredentials {
+    AwsCredentials::new(
+        access_key_id,
+        secret_access_key,
+        token,
+        UTC::now() + Duration::days(365 * 100),
+    )
+}
+
+/// A pair of keys kept in memory.
+#[derive(Debug, Clone)]
+pub struct StaticProvider {
+    credentials: AwsCredentials,
+}
+
+impl StaticProvider {
+    pub fn new(access_key_id: &str, secret_access_key: &str) -> StaticProvider {
+        StaticProvider {
+            credentials: long_lived(access_key_id, secret_access_key, None),
+        }
+    }
+}
+
+impl AwsCredentialsProvider for StaticProvider {
+    fn credentials(&self) -> Result<AwsCredentials, CredentialsError> {
+        Ok(self.credentials.clone())
+    }
+}
+
+/// A profile of the ct credentials file.
+///
+/// ```ini
+/// [default]
+/// access_key_id = ae2600e3194ec00fbcfb
+/// secret_access_key = c760152a28e608eb5b6d3bac02dd2780bff087cb
+/// ```
+#[derive(Debug, Clone)]
+pub struct CtProfileProvider {
+    path: PathBuf,
+    profile: String,
+}
+
+impl CtProfileProvider {
+    /// `$CT_CREDENTIALS_FILE` or `~/.ct/credentials`, profile `$CT_PROFILE` or `default`.
+    pub fn new() -> CtProfileProvider {
+        let profile = env::var("CT_PROFILE").unwrap_or("default".to_string());
+        CtProfileProvider::named(&profile)
+    }
+
+    /// `profile` of `$CT_CREDENTIALS_FILE` or `~/.ct/credentials`.
+    pub fn named(profile: &str) -> CtProfileProvider {
+        let path = match env::var("CT_CREDENTIALS_FILE") {
+            Ok(path) => PathBuf::from(path),
+            Err(_) => env::home_dir().unwrap_or_default().join(CREDENTIALS_FILE),
+        };
+        CtProfileProvider::with_profile(path, profile)
+    }
+
+    pub fn with_profile<P: AsRef<Path>>(path: P, profile: &str) -> CtProfileProvider {
+        CtProfileProvider {
+            path: path.as_ref().to_path_buf(),
+            profile: profile.to_string(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn profile(&self) -> &str {
+        &self.profile
+    }
+
+    /// Store a new key pair in the profile, see `write_profile`.
+    pub fn save(&self, access_key_id: &str, secret_access_key: &str) -> io::Result<()> {
+        write_profile(&self.path, &self.profile, access_key_id, secret_access_key)
+    }
+}
+
+impl AwsCredentialsProvider for CtProfileProvider {
+    fn credentials(&self) -> Result<AwsCredentials, CredentialsError> {
+        let mut content = String::new();
+        if let Err(err) = File::open(&self.path).and_then(|mut f| f.read_to_string(&mut content)) {
+            return Err(CredentialsError::new(&format!("{}: {}", self.path.display(), err)));
+        }
+        parse_profile(&content, &self.profile).map_err(|err| {
+            CredentialsError::new(&format!("{}: {}", self.path.display(), err))
+        })
+    }
+}
+
+/// Read the keys of `profile` in the content of a credentials file.
+pub fn parse_profile(content: &str, profile: &str) -> Result<AwsCredentials, String> {
+    let mut current = None;
+    let mut access_key_id = None;
+    let mut secret_access_key = None;
+    let mut session_token = None;
+
+    for line in content.lines().map(|line| line.trim()) {
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if line.starts_with('[') && line.ends_with(']') {
+            current = Some(line[1..line.len() - 1].trim().to_string());
+            continue;
+        }
+        if current.as_ref().map(|name| name.as_str()) != Some(profile) {
+            continue;
+        }
+        let mut pair = line.splitn(2, '=');
+        let name = pair.next().unwrap().trim();
+        let value = match pair.next() {
+            Some(value) => value.trim().to_string(),
+            None => continue,
+        };
+        match name {
+            "access_key_id" | "aws_access_key_id" => access_key_id = Some(value),
+            "secret_access_key" | "aws_secret_access_key" => secret_access_key = Some(value),
+            "session_token" | "aws_session_token" => session_token = Some(value),
+            _ => {}
+        }
+    }
+
+    match (access_key_id, secret_access_key) {
+        (Some(ak), Some(sk)) => Ok(long_lived(&ak, &sk, session_token)),
+        (None, _) => Err(format!("no access_key_id in profile [{}]", profile)),
+        (_, None) => Err(format!("no secret_access_key in profile [{}]", profile)),
+    }
+}
+
+/// Write the keys of `profile` into the credentials file at `path`.
+///
+/// The other profiles and settings of the file are kept. The file is created
+/// if needed, readable by its owner only. The new content is written to a temporary
+/// file of the same directory, then renamed over `path`, so that readers never see
+/// a partial file.
+pub fn write_profile(
+    path: &Path,
+    profile: &str,
+    access_key_id: &str,
+    secret_access_key: &str,
+) -> io::Result<()> {
+    let mut content = String::new();
+    match File::open(path) {
+        Ok(mut file) => {
+            try!(file.read_to_string(&mut content));
+        }
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => {
+            if let Some(parent) = path.parent() {
+                try!(fs::create_dir_all(parent));
+            }
+        }
+        Err(err) => return Err(err),
+    }
+
+    let content = update_profile(&content, profile, access_key_id, secret_access_key);
+    let name = path.file_name().and_then(|name| name.to_str()).unwrap_or("credentials");
+    let temp = path.with_file_name(format!(".{}.{}.tmp", name, process::id()));
+    let written = create_private(&temp)
+        .and_then(|mut file| file.write_all(content.as_bytes()).and_then(|_| file.sync_all()))
+        .and_then(|_| fs::rename(&temp, path));
+    if written.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    written
+}
+
+/// Create a new file, readable by its owner only.
+fn create_private(path: &Path) -> io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)
+}
+
+/// Replace the keys of `profile` in the content of a credentials file.
+pub fn update_profile(
+    content: &str,
+    profile: &str,
+    access_key_id: &str,
+    secret_access_key: &str,
+) -> String {
+    let keys = format!(
+        "access_key_id = {}\nsecret_access_key = {}\n",
+        access_key_id, secret_access_key
+    );
+    let mut out = String::with_capacity(content.len() + keys.len());
+    let mut found = false;
+    let mut in_profile = false;
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') && trimmed.ends_with(']') {
+            in_profile = trimmed[1..trimmed.len() - 1].trim() == profile;
+            out.push_str(line);
+            out.push('\n');
+            if in_profile {
+                found = true;
+                out.push_str(&keys);
+            }
+            continue;
+        }
+        if in_profile {
+            let name = trimmed.splitn(2, '=').next().unwrap().trim();
+            match name {
+                "access_key_id" | "aws_access_key_id" | "secret_access_key"
+                | "aws_secret_access_key" | "session_token" | "aws_session_token" => continue,
+                _ => {}
+            }
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+
+    if !found {
+        if !out.is_empty() && !out.ends_with("\n\n") {
+            out.push('\n');
+        }
+        out.push_str(&format!("[{}]\n", profile));
+        out.push_str(&keys);
+    }
+    out
+}
+
+/// Credentials printed by an external command, e.g. a password manager.
+///
+/// The command is run by the shell and must print
+///
+/// ```json
+/// {
+///     "Version": 1,
+///     "AccessKeyId": "ae2600e3194ec00fbcfb",
+///     "SecretAccessKey": "c760152a28e608eb5b6d3bac02dd2780bff087cb",
+///     "SessionToken": "optional",
+///     "Expiration": "2017-12-01T00:00:00Z"
+/// }
+/// ```
+///
+/// The credentials are cached until five minutes before `Expiration`, or for
+/// the life of the provider without it.
+#[derive(Debug)]
+pub struct ProcessProvider {
+    command: String,
+    cache: RefCell<Option<AwsCredentials>>,
+}
+
+impl ProcessProvider {
+    pub fn new(command: &str) -> ProcessProvider {
+        ProcessProvider {
+            command: command.to_string(),
+            cache: RefCell::new(None),
+        }
+    }
+
+    /// The command of `CT_CREDENTIAL_PROCESS`, if any.
+    pub fn from_env() -> Option<ProcessProvider> {
+        env::var("CT_CREDENTIAL_PROCESS")
+            .ok()
+            .map(|command| ProcessProvider::new(&command))
+    }
+
+    fn run(&self) -> Result<AwsCredentials, CredentialsError> {
+        let output = if cfg!(windows) {
+            Command::new("cmd").arg("/C").arg(&self.command).output()
+        } else {
+            Command::new("sh").arg("-c").arg(&self.command).output()
+        };
+        let output = match output {
+            Ok(output) => output,
+            Err(err) => {
+                return Err(CredentialsError::new(&format!("`{}`: {}", self.command, err)))
+            }
+        };
+        if !output.status.success() {
+            return Err(CredentialsError::new(&format!(
+                "`{}` {}: {}",
+                self.command,
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        parse_process_output(&String::from_utf8_lossy(&output.stdout))
+            .map_err(|err| CredentialsError::new(&format!("`{}`: {}", self.command, err)))
+    }
+}
+
+impl AwsCredentialsProvider for ProcessProvider {
+    fn credentials(&self) -> Result<AwsCredentials, CredentialsError> {
+        if let Some(ref credentials) = *self.cache.borrow() {
+            if fresh(credentials) {
+                return Ok(credentials.clone());
+            }
+        }
+        let credentials = try!(self.run());
+        *self.cache.borrow_mut() = Some(credentials.clone());
+        Ok(credentials)
+    }
+}
+
+/// Whether `credentials` are valid for five more minutes at least.
+fn fresh(credentials: &AwsCredentials) -> bool {
+    *credentials.expires_at() > UTC::now() + Duration::minutes(5)
+}
+
+/// Read the JSON printed by a credential process.
+pub fn parse_process_output(output: &str) -> Result<AwsCredentials, String> {
+    let json = try!(Json::from_str(output).map_err(|err| format!("{}", err)));
+    let field = |name: &str| json.find(name).and_then(|value| value.as_string());
+
+    if let Some(version) = json.find("Version").and_then(|value| value.as_i64()) {
+        if version != 1 {
+            return Err(format!("unsupported Version {}", version));
+        }
+    }
+    let access_key_id = try!(field("AccessKeyId").ok_or("no AccessKeyId".to_string()));
+    let secret_access_key = try!(field("SecretAccessKey").ok_or("no SecretAccessKey".to_string()));
+    let token = field("SessionToken").map(|token| token.to_string());
+
+    match field("Expiration") {
+        Some(expiration) => {
+            let expires_at = try!(
+                DateTime::parse_from_rfc3339(expiration)
+                    .map_err(|err| format!("Expiration {}: {}", expiration, err))
+            );
+            Ok(AwsCredentials::new(
+                access_key_id,
+                secret_access_key,
+                token,
+                expires_at.with_timezone(&UTC),
+            ))
+        }
+        None => Ok(long_lived(access_key_id, secret_access_key, token)),
+    }
+}
+
+/// The first of several named providers which returns credentials.
+pub struct CredentialsChain {
+    sources: Vec<(String, Box<AwsCredentialsProvider + Send>)>,
+}
+
+impl CredentialsChain {
+    /// An empty chain, see `with`.
+    pub fn new() -> CredentialsChain {
+        CredentialsChain { sources: vec![] }
+    }
+
+    /// Try `provider` after the providers already in the chain.
+    pub fn with<P>(mut self, name: &str, provider: P) -> CredentialsChain
+    where
+        P: AwsCredentialsProvider + Send + 'static,
+    {
+        self.sources.push((name.to_string(), Box::new(provider)));
+        self
+    }
+
+    /// Keep the credentials of the chain until they expire, see `CachedProvider`.
+    pub fn cached(self) -> CachedProvider<CredentialsChain> {
+        CachedProvider::new(self)
+    }
+
+    /// The default chain, reading `profile` of the ct credentials file.
+    pub fn for_profile(profile: CtProfileProvider) -> CredentialsChain {
+        let mut chain = CredentialsChain::new()
+            .with("environment", EnvironmentProvider)
+            .with("ct profile", profile);
+        if let Some(process) = ProcessProvider::from_env() {
+            chain = chain.with("credential process", process);
+        }
+        if let Ok(profile) = ProfileProvider::new() {
+            chain = chain.with("aws profile", profile);
+        }
+        chain.with("instance profile", IamProvider)
+    }
+
+    /// Names of the providers, in order.
+    pub fn sources(&self) -> Vec<&str> {
+        self.sources.iter().map(|&(ref name, _)| name.as_str()).collect()
+    }
+}
+
+impl Default for CredentialsChain {
+    /// Environment (`AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY`), the ct
+    /// credentials file, `CT_CREDENTIAL_PROCESS`, `~/.aws/credentials`, then the
+    /// IAM role of the instance.
+    ///
+    /// Every call asks the sources again, wrap the chain with `cached` to keep the
+    /// credentials, e.g. of the instance profile.
+    fn default() -> CredentialsChain {
+        CredentialsChain::for_profile(CtProfileProvider::new())
+    }
+}
+
+impl AwsCredentialsProvider for CredentialsChain {
+    fn credentials(&self) -> Result<AwsCredentials, CredentialsError> {
+        let mut tried = Vec::with_capacity(self.sources.len());
+        for &(ref name, ref provider) in &self.sources {
+            match provider.credentials() {
+                Ok(credentials) => {
+                    debug!("Credentials from {}", name);
+                    return Ok(credentials);
+                }
+                Err(err) => tried.push(format!("{} ({})", name, err.message)),
+            }
+        }
+        Err(CredentialsError::new(&format!(
+            "No credentials found, tried: {}",
+            tried.join(", ")
+        )))
+    }
+}
+
+/// The credentials of `provider`, kept until five minutes before they expire.
+///
+/// Errors are not cached: the next call asks `provider` again.
+pub struct CachedProvider<P> {
+    provider: P,
+    cache: RefCell<Option<AwsCredentials>>,
+}
+
+impl<P: AwsCredentialsProvider> CachedProvider<P> {
+    pub fn new(provider: P) -> CachedProvider<P> {
+        CachedProvider {
+            provider: provider,
+            cache: RefCell::new(None),
+        }
+    }
+}
+
+impl<P: AwsCredentialsProvider> AwsCredentialsProvider for CachedProvider<P> {
+    fn credentials(&self) -> Result<AwsCredentials, CredentialsError> {
+        if let Some(ref credentials) = *self.cache.borrow() {
+            if fresh(credentials) {
+                return Ok(credentials.clone());
+            }
+        }
+        let credentials = try!(self.provider.credentials());
+        *self.cache.borrow_mut() = Some(credentials.clone());
+        Ok(credentials)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use aws_sdk_rust::aws::common::credentials::{AwsCredentials, AwsCredentialsProvider,
+                                                 CredentialsError};
+    use super::*;
+
+    struct Missing;
+
+    impl AwsCredentialsProvider for Missing {
+        fn credentials(&self) -> Result<AwsCredentials, CredentialsError> {
+            Err(CredentialsError::new("not set"))
+        }
+    }
+
+    #[test]
+    fn profile() {
+        let content = "[default]\naccess_key_id = ak\nsecret_access_key = sk\n\n\
+                       [other]\n# comment\naws_access_key_id=ak2\naws_secret_access_key=sk2\n";
+        let credentials = parse_profile(content, "other").unwrap();
+        assert_eq!(credentials.aws_access_key_id(), "ak2");
+        assert_eq!(credentials.aws_secret_access_key(), "sk2");
+        assert!(parse_profile(content, "missing").is_err());
+    }
+
+    #[test]
+    fn replace_profile() {
+        let content = "[default]\naccess_key_id = old\nsecret_access_key = old\nregion = bj2\n\n\
+                       [other]\naccess_key_id = ak2\nsecret_access_key = sk2\n";
+        let updated = update_profile(content, "default", "new", "secret");
+        let credentials = parse_profile(&updated, "default").unwrap();
+        assert_eq!(credentials.aws_access_key_id(), "new");
+        assert_eq!(credentials.aws_secret_access_key(), "secret");
+        assert!(updated.contains("region = bj2"));
+        assert_eq!(parse_profile(&updated, "other").unwrap().aws_access_key_id(), "ak2");
+
+        let added = update_profile(content, "rotated", "ak3", "sk3");
+        assert_eq!(parse_profile(&added, "rotated").unwrap().aws_access_key_id(), "ak3");
+        assert_eq!(parse_profile(&added, "default").unwrap().aws_access_key_id(), "old");
+    }
+
+    #[test]
+    fn write_profile_file() {
+        let dir = env::temp_dir().join(format!("ct-credentials-{}", process::id()));
+        let path = dir.join("credentials");
+        write_profile(&path, "default", "ak", "sk").unwrap();
+        write_profile(&path, "other", "ak2", "sk2").unwrap();
+
+        let provider = CtProfileProvider::with_profile(&path, "default");
+        assert_eq!(provider.credentials().unwrap().aws_access_key_id(), "ak");
+        let provider = CtProfileProvider::with_profile(&path, "other");
+        assert_eq!(provider.credentials().unwrap().aws_access_key_id(), "ak2");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn process_output() {
+        let output = r#"{"Version": 1, "AccessKeyId": "ak", "SecretAccessKey": "sk",
+                         "Expiration": "2017-12-01T00:00:00Z"}"#;
+        let credentials = parse_process_output(output).unwrap();
+        assert_eq!(credentials.aws_access_key_id(), "ak");
+        assert_eq!(format!("{}", credentials.expires_at()), "2017-12-01 00:00:00 UTC");
+        assert!(parse_process_output(r#"{"Version": 2}"#).is_err());
+        assert!(parse_process_output(r#"{"AccessKeyId": "ak"}"#).is_err());
+    }
+
+    #[test]
+    fn chain_reports_sources() {
+        let chain = CredentialsChain::new().with("first", Missing).with("second", Missing);
+        let err = chain.credentials().unwrap_err();
+        assert_eq!(
+            err.message,
+            "No credentials found, tried: first (not set), second (not set)"
+        );
+
+        let chain = chain.with("static", StaticProvider::new("ak", "sk"));
+        assert_eq!(chain.credentials().unwrap().aws_access_key_id(), "ak");
+        assert_eq!(chain.sources(), vec!["first", "second", "static"]);
+
+        let chain = CredentialsChain::default();
+        assert_eq!(chain.sources().last(), Some(&"instance profile"));
+    }
+
+    struct Counting {
+        calls: Rc<Cell<u32>>,
+        expires_in: Duration,
+    }
+
+    impl AwsCredentialsProvider for Counting {
+        fn credentials(&self) -> Result<AwsCredentials, CredentialsError> {
+            self.calls.set(self.calls.get() + 1);
+            Ok(AwsCredentials::new("ak", "sk", None, UTC::now() + self.expires_in))
+        }
+    }
+
+    #[test]
+    fn cached_credentials() {
+        let calls = Rc::new(Cell::new(0));
+        let cached = CachedProvider::new(Counting {
+            calls: calls.clone(),
+            expires_in: Duration::hours(1),
+        });
+        cached.credentials().unwrap();
+        cached.credentials().unwrap();
+        assert_eq!(calls.get(), 1);
+
+        let expiring = CachedProvider::new(Counting {
+            calls: calls.clone(),
+            expires_in: Duration::minutes(1),
+        });
+        expiring.credentials().unwrap();
+        expiring.credentials().unwrap();
+        assert_eq!(calls.get(), 3);
+    }
+}
@@ -19,34 +19,47 @@
 //! | Fixed  | Variable  |   Fixed   |
 //! +--------+-----------+-----------+
 //! ```
-
-use std::io;
+//!
+//! Only the AEAD ciphers (`aes-128-gcm`, `aes-256-gcm`, `chacha20-ietf-poly1305` and,
+//! with the `miscreant` feature, `aes-128-pmac-siv`, `aes-256-pmac-siv`) authenticate
+//! the payload: a wrong password, a wrong method or tampered data gives
+//! `CtError::Authentication`. The stream ciphers (`aes-*-cfb*`, `rc4`, `rc4-md5`,
+//! `chacha20`, `salsa20`, `table`...) decrypt with any key and return garbage instead.
 
 pub use ct::crypto::CipherType;
 use ct::crypto::{self, CipherCategory, CryptoMode};
 use ct::crypto::StreamCipher;
-
+use ct::crypto::cipher::Error;
+use ct::error::CtError;
+
+/// A failed AEAD tag check means the key (password) or the data is wrong
+fn cipher_error(err: Error) -> CtError {
+    match err {
+        Error::AeadDecryptFailed => CtError::Authentication,
+        err => CtError::Crypto(format!("{}", err)),
+    }
+}
 
 /// Encrypt payload into ShadowSocks UDP encrypted packet
-pub fn encrypt_payload(t: CipherType, key: &[u8], payload: &[u8]) -> io::Result<Vec<u8>> {
+pub fn encrypt_payload(t: CipherType, key: &[u8], payload: &[u8]) -> Result<Vec<u8>, CtError> {
     match t.category() {
         CipherCategory::Stream => encrypt_payload_stream(t, key, payload),
         CipherCategory::Aead => encrypt_payload_aead(t, key, payload),
     }
 }
 
-fn encrypt_payload_stream(t: CipherType, key: &[u8], payload: &[u8]) -> io::Result<Vec<u8>> {
+fn encrypt_payload_stream(t: CipherType, key: &[u8], payload: &[u8]) -> Result<Vec<u8>, CtError> {
     let iv = t.gen_init_vec();
     let mut cipher = crypto::new_stream(t, key, &iv, CryptoMode::Encrypt);
 
     let mut send_payload = Vec::with_capacity(iv.len() + payload.len());
     send_payload.extend_from_slice(&iv);
-    cipher.update(&payload[..], &mut send_payload)?;
-    cipher.finalize(&mut send_payload)?;
+    cipher.update(&payload[..], &mut send_payload).map_err(cipher_error)?;
+    cipher.finalize(&mut send_payload).map_err(cipher_error)?;
     Ok(send_payload)
 }
 
-fn encrypt_payload_aead(t: CipherType, key: &[u8], payload: &[u8]) -> io::Result<Vec<u8>> {
+fn encrypt_payload_aead(t: CipherType, key: &[u8], payload: &[u8]) -> Result<Vec<u8>, CtError> {
     let salt = t.gen_salt();
     let tag_size = t.tag_size();
     let mut cipher = crypto::new_aead_encryptor(t, key, &salt);
@@ -66,18 +79,17 @@ fn encrypt_payload_aead(t: CipherType, key: &[u8], payload: &[u8]) -> io::Result
 }
 
 /// Decrypt payload from ShadowSocks UDP encrypted packet
-pub fn decrypt_payload(t: CipherType, key: &[u8], payload: &[u8]) -> io::Result<Vec<u8>> {
+pub fn decrypt_payload(t: CipherType, key: &[u8], payload: &[u8]) -> Result<Vec<u8>, CtError> {
     match t.category() {
         CipherCategory::Stream => decrypt_payload_stream(t, key, payload),
         CipherCategory::Aead => decrypt_payload_aead(t, key, payload),
     }
 }
 
-fn decrypt_payload_stream(t: CipherType, key: &[u8], payload: &[u8]) -> io::Result<Vec<u8>> {
+fn decrypt_payload_stream(t: CipherType, key: &[u8], payload: &[u8]) -> Result<Vec<u8>, CtError> {
     let iv_size = t.iv_size();
     if payload.len() < iv_size {
-        let err = io::Error::new(io::ErrorKind::Other, "udp packet too short");
-        return Err(err);
+        return Err(CtError::Crypto("encrypted payload too short".to_string()));
     }
 
     let iv = &payload[..iv_size];
@@ -86,19 +98,18 @@ fn decrypt_payload_stream(t: CipherType, key: &[u8], payload: &[u8]) -> io::Resu
     let mut cipher = crypto::new_stream(t, key, iv, CryptoMode::Decrypt);
 
     let mut recv_payload = Vec::with_capacity(data.len());
-    cipher.update(data, &mut recv_payload)?;
-    cipher.finalize(&mut recv_payload)?;
+    cipher.update(data, &mut recv_payload).map_err(cipher_error)?;
+    cipher.finalize(&mut recv_payload).map_err(cipher_error)?;
 
     Ok(recv_payload)
 }
 
-fn decrypt_payload_aead(t: CipherType, key: &[u8], payload: &[u8]) -> io::Result<Vec<u8>> {
+fn decrypt_payload_aead(t: CipherType, key: &[u8], payload: &[u8]) -> Result<Vec<u8>, CtError> {
     let tag_size = t.tag_size();
     let salt_size = t.salt_size();
 
     if payload.len() < tag_size + salt_size {
-        let err = io::Error::new(io::ErrorKind::Other, "udp packet too short");
-        return Err(err);
+        return Err(CtError::Crypto("encrypted payload too short".to_string()));
     }
 
     let salt = &payload[..salt_size];
@@ -109,7 +120,32 @@ fn decrypt_payload_aead(t: CipherType, key: &[u8], payload: &[u8]) -> io::Result
     let mut cipher = crypto::new_aead_decryptor(t, key, salt);
 
     let mut recv_payload = vec![0u8; data_length];
-    cipher.decrypt(data, &mut recv_payload, tag)?;
+    cipher.decrypt(data, &mut recv_payload, tag).map_err(cipher_error)?;
 
     Ok(recv_payload)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrong_password() {
+        let t = CipherType::Aes256Gcm;
+        let key = t.bytes_to_key(b"password");
+        let payload = encrypt_payload(t, &key, b"hello").unwrap();
+        assert_eq!(decrypt_payload(t, &key, &payload).unwrap(), b"hello");
+
+        let wrong = t.bytes_to_key(b"wrong password");
+        match decrypt_payload(t, &wrong, &payload) {
+            Err(CtError::Authentication) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+
+        // Stream ciphers can't tell a wrong key
+        let t = CipherType::Aes256Cfb;
+        let payload = encrypt_payload(t, &t.bytes_to_key(b"password"), b"hello").unwrap();
+        let decrypted = decrypt_payload(t, &t.bytes_to_key(b"wrong password"), &payload).unwrap();
+        assert!(decrypted != b"hello");
+    }
+}
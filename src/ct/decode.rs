@@ -0,0 +1,375 @@
+// Copyright 2017 Gitai<i@gitai.me> All rights reserved.
+//
+// Permission is hereby granted, free of charge, to any person
+// obtaining a copy of this software and associated documentation
+// files (the "Software"), to deal in the Software without restriction,
+// including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall
+// be included in all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND,
+// EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES
+// OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.
+// IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR
+// ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF
+// CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION
+// WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+//! Declarative decoding of XML responses
+//!
+//! A response is read into a tree of `Element`, then decoded by `FromXml`.
+//! Structs declare the elements they read with `xml_struct!`, each entry ends
+//! with a comma and `[field]` pushes every occurrence of a repeated element:
+//!
+//! ```ignore
+//! xml_struct! {
+//!     ListUsersOutput {
+//!         "Users" => users,
+//!         "IsTruncated" => is_truncated,
+//!         "Marker" => marker,
+//!     }
+//! }
+//!
+//! let out: ListUsersOutput = try!(decode(&body, &["ListUsersResult"]));
+//! ```
+//!
+//! Elements may come in any order, unknown elements are skipped and missing
+//! ones keep their `Default` value. Errors report the path of the element, e.g.
+//! `ListUsersResponse/ListUsersResult/IsTruncated: invalid boolean "yes"`.
+
+use std::fmt;
+use std::error::Error;
+
+use xml::reader::{EventReader, XmlEvent};
+
+/// An element of a parsed document
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Element {
+    /// Local name, without namespace
+    pub name: String,
+    /// Concatenated text and CDATA
+    pub text: String,
+    pub children: Vec<Element>,
+}
+
+impl Element {
+    /// First child named `name`
+    pub fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+}
+
+/// Error while decoding the element at `path`
+#[derive(Debug, Clone, PartialEq)]
+pub struct XmlError {
+    pub path: String,
+    pub message: String,
+}
+
+impl XmlError {
+    pub fn new(path: &str, message: &str) -> XmlError {
+        XmlError {
+            path: path.to_string(),
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for XmlError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.path.is_empty() {
+            true => write!(f, "{}", self.message),
+            false => write!(f, "{}: {}", self.path, self.message),
+        }
+    }
+}
+
+impl Error for XmlError {
+    fn description(&self) -> &str {
+        &self.message
+    }
+}
+
+fn path_of(stack: &[Element]) -> String {
+    stack
+        .iter()
+        .map(|element| element.name.as_str())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Read `body` into a tree of elements.
+pub fn parse(body: &str) -> Result<Element, XmlError> {
+    let mut stack: Vec<Element> = Vec::new();
+    for event in EventReader::from_str(body) {
+        match event {
+            Ok(XmlEvent::StartElement { name, .. }) => stack.push(Element {
+                name: name.local_name,
+                ..Default::default()
+            }),
+            Ok(XmlEvent::Characters(text)) | Ok(XmlEvent::CData(text)) => {
+                if let Some(element) = stack.last_mut() {
+                    element.text.push_str(&text);
+                }
+            }
+            Ok(XmlEvent::EndElement { .. }) => {
+                let element = stack.pop().unwrap();
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None => return Ok(element),
+                }
+            }
+            Ok(_) => {}
+            Err(err) => return Err(XmlError::new(&path_of(&stack), &format!("{}", err))),
+        }
+    }
+    Err(XmlError::new(&path_of(&stack), "unexpected end of document"))
+}
+
+/// Decode the element at `path` below the root of `body`, e.g. `&["ListUsersResult"]`.
+pub fn decode<T: FromXml>(body: &str, path: &[&str]) -> Result<T, XmlError> {
+    let root = try!(parse(body));
+    let mut element = &root;
+    let mut current = root.name.clone();
+    for name in path {
+        element = match element.child(name) {
+            Some(child) => child,
+            None => return Err(XmlError::new(&current, &format!("missing element {}", name))),
+        };
+        current = format!("{}/{}", current, name);
+    }
+    T::from_xml(element, &current)
+}
+
+/// A type decoded from an element
+pub trait FromXml: Sized {
+    /// Decode `element`, whose path is `path`.
+    fn from_xml(element: &Element, path: &str) -> Result<Self, XmlError>;
+}
+
+impl FromXml for String {
+    fn from_xml(element: &Element, _: &str) -> Result<String, XmlError> {
+        Ok(element.text.trim().to_string())
+    }
+}
+
+impl FromXml for bool {
+    fn from_xml(element: &Element, path: &str) -> Result<bool, XmlError> {
+        match element.text.trim() {
+            "true" | "True" | "TRUE" => Ok(true),
+            "false" | "False" | "FALSE" | "" => Ok(false),
+            text => Err(XmlError::new(path, &format!("invalid boolean {:?}", text))),
+        }
+    }
+}
+
+macro_rules! from_xml_number {
+    ($($ty:ty),*) => {
+        $(
+            impl FromXml for $ty {
+                fn from_xml(element: &Element, path: &str) -> Result<$ty, XmlError> {
+                    element.text.trim().parse().map_err(|_| {
+                        XmlError::new(path, &format!("invalid number {:?}", element.text.trim()))
+                    })
+                }
+            }
+        )*
+    };
+}
+
+from_xml_number!(u8, u16, u32, u64, i32, i64, f64);
+
+impl<T: FromXml> FromXml for Option<T> {
+    fn from_xml(element: &Element, path: &str) -> Result<Option<T>, XmlError> {
+        T::from_xml(element, path).map(Some)
+    }
+}
+
+/// A wrapper element, e.g. `<Users><member>..</member></Users>`.
+impl<T: FromXml> FromXml for Vec<T> {
+    fn from_xml(element: &Element, path: &str) -> Result<Vec<T>, XmlError> {
+        element
+            .children
+            .iter()
+            .enumerate()
+            .map(|(index, child)| {
+                T::from_xml(child, &format!("{}/{}[{}]", path, child.name, index))
+            })
+            .collect()
+    }
+}
+
+/// Implement `FromXml` for a struct with `Default`, see the module documentation.
+#[macro_export]
+macro_rules! xml_struct {
+    ($ty:ident { $($body:tt)* }) => {
+        impl $crate::ct::decode::FromXml for $ty {
+            fn from_xml(
+                element: &$crate::ct::decode::Element,
+                path: &str,
+            ) -> Result<$ty, $crate::ct::decode::XmlError> {
+                let mut obj = $ty::default();
+                for child in &element.children {
+                    xml_struct!(@field obj, child, path, $($body)*);
+                }
+                Ok(obj)
+            }
+        }
+    };
+    (@field $obj:ident, $child:ident, $path:ident, $name:expr => [$field:ident], $($rest:tt)*) => {
+        if $child.name == $name {
+            let path = format!("{}/{}[{}]", $path, $child.name, $obj.$field.len());
+            $obj.$field.push(try!($crate::ct::decode::FromXml::from_xml($child, &path)));
+            continue;
+        }
+        xml_struct!(@field $obj, $child, $path, $($rest)*);
+    };
+    (@field $obj:ident, $child:ident, $path:ident, $name:expr => $field:ident, $($rest:tt)*) => {
+        if $child.name == $name {
+            let path = format!("{}/{}", $path, $child.name);
+            $obj.$field = try!($crate::ct::decode::FromXml::from_xml($child, &path));
+            continue;
+        }
+        xml_struct!(@field $obj, $child, $path, $($rest)*);
+    };
+    (@field $obj:ident, $child:ident, $path:ident,) => {};
+}
+
+/// `<Error>` document, alone or wrapped in `<ErrorResponse>`
+#[derive(Debug, Default, Clone)]
+pub struct ErrorDocument {
+    pub code: String,
+    pub message: String,
+    pub request_id: String,
+    pub resource: String,
+}
+
+xml_struct! {
+    ErrorDocument {
+        "Code" => code,
+        "Message" => message,
+        "RequestId" => request_id,
+        "Resource" => resource,
+    }
+}
+
+impl ErrorDocument {
+    /// Decode the error of `body`, `None` if it is not an error document.
+    pub fn from_body(body: &str) -> Option<ErrorDocument> {
+        let root = match parse(body) {
+            Ok(root) => root,
+            Err(_) => return None,
+        };
+        if root.name == "Error" {
+            return ErrorDocument::from_xml(&root, "Error").ok();
+        }
+        let mut error = match root.child("Error") {
+            Some(error) => match ErrorDocument::from_xml(error, "ErrorResponse/Error") {
+                Ok(error) => error,
+                Err(_) => return None,
+            },
+            None => return None,
+        };
+        if error.request_id.is_empty() {
+            if let Some(request_id) = root.child("RequestId") {
+                error.request_id = request_id.text.trim().to_string();
+            }
+        }
+        Some(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Default)]
+    struct Member {
+        name: String,
+        size: u64,
+        enabled: bool,
+    }
+
+    xml_struct! {
+        Member {
+            "Name" => name,
+            "Size" => size,
+            "Enabled" => enabled,
+        }
+    }
+
+    #[derive(Debug, Default)]
+    struct Listing {
+        members: Vec<Member>,
+        rules: Vec<String>,
+        marker: Option<String>,
+    }
+
+    xml_struct! {
+        Listing {
+            "Members" => members,
+            "Rule" => [rules],
+            "Marker" => marker,
+        }
+    }
+
+    #[test]
+    fn decode_tolerant() {
+        let body = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ListResponse xmlns="http://oos.ctyunapi.cn/doc/2006-03-01/">
+  <ListResult>
+    <Rule>a</Rule>
+    <Unknown><Nested>1</Nested></Unknown>
+    <Members>
+      <member><Size>10</Size><Name>first</Name></member>
+      <member><Name>second</Name><Enabled>true</Enabled><Extra/></member>
+    </Members>
+    <Rule><![CDATA[b]]></Rule>
+  </ListResult>
+</ListResponse>"#;
+        let listing: Listing = decode(body, &["ListResult"]).unwrap();
+        assert_eq!(listing.members.len(), 2);
+        assert_eq!(listing.members[0].name, "first");
+        assert_eq!(listing.members[0].size, 10);
+        assert!(listing.members[1].enabled);
+        assert_eq!(listing.rules, vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(listing.marker, None);
+    }
+
+    #[test]
+    fn decode_error_path() {
+        let body = "<ListResponse><ListResult><Members>\
+                    <member><Name>a</Name></member><member><Size>ten</Size></member>\
+                    </Members></ListResult></ListResponse>";
+        let err = decode::<Listing>(body, &["ListResult"]).unwrap_err();
+        assert_eq!(err.path, "ListResponse/ListResult/Members/member[1]/Size");
+        assert_eq!(
+            format!("{}", err),
+            "ListResponse/ListResult/Members/member[1]/Size: invalid number \"ten\""
+        );
+
+        let err = decode::<Listing>(body, &["Missing"]).unwrap_err();
+        assert_eq!(format!("{}", err), "ListResponse: missing element Missing");
+
+        let err = parse("<ListResponse><ListResult></ListResponse>").unwrap_err();
+        assert_eq!(err.path, "ListResponse/ListResult");
+    }
+
+    #[test]
+    fn error_document() {
+        let body = "<ErrorResponse><Error><Code>NoSuchEntity</Code>\
+                    <Message>user not found</Message></Error>\
+                    <RequestId>42</RequestId></ErrorResponse>";
+        let error = ErrorDocument::from_body(body).unwrap();
+        assert_eq!(error.code, "NoSuchEntity");
+        assert_eq!(error.request_id, "42");
+
+        let body = "<Error><Code>NoSuchKey</Code><RequestId>7</RequestId></Error>";
+        assert_eq!(ErrorDocument::from_body(body).unwrap().request_id, "7");
+        assert!(ErrorDocument::from_body("").is_none());
+    }
+}
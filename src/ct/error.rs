@@ -0,0 +1,237 @@
+// Copyright 2017 Gitai<i@gitai.me> All rights reserved.
+//
+// Permission is hereby granted, free of charge, to any person
+// obtaining a copy of this software and associated documentation
+// files (the "Software"), to deal in the Software without restriction,
+// including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall
+// be included in all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND,
+// EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES
+// OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.
+// IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR
+// ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF
+// CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION
+// WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+//! Errors of the CTYun OOS SDK
+//!
+//! All `CTClient*` traits return `CtError`, so callers can tell a wrong
+//! password (`Authentication`) from a network failure (`Http`).
+//!
+//! ```
+//! match s3.get_object_securely(&request, None) {
+//!     Ok(out) => println!("{:#?}", out),
+//!     Err(CtError::Authentication) => println!("Wrong password"),
+//!     Err(err) => println!("{}", err),
+//! }
+//! ```
+
+use std::io;
+use std::fmt;
+use std::error::Error;
+
+use aws_sdk_rust::aws::common::credentials::CredentialsError;
+use aws_sdk_rust::aws::common::xmlutil::XmlParseError;
+use aws_sdk_rust::aws::errors::aws::AWSError;
+use aws_sdk_rust::aws::errors::s3::S3Error;
+
+use ct::decode::{ErrorDocument, XmlError};
+
+/// Result of the `CTClient*` operations
+pub type CtResult<T> = Result<T, CtError>;
+
+#[derive(Debug)]
+pub enum CtError {
+    /// Error document returned by OOS.
+    Service {
+        /// HTTP status, `None` when unknown.
+        ///
+        /// The errors of the `S3Client` operations drop the status, it is then
+        /// inferred from `code`, see `status_of`.
+        status: Option<u16>,
+        code: String,
+        message: String,
+        request_id: String,
+    },
+    /// The request could not be sent or the response could not be read.
+    Http(String),
+    /// The response is not the expected XML document.
+    Xml(String),
+    /// No credentials could be loaded.
+    Credentials(String),
+    /// The payload could not be encrypted or decrypted.
+    Crypto(String),
+    /// The payload failed authentication: wrong password, wrong method or tampered data.
+    /// Only the AEAD ciphers can detect it, see `ct::crypto_io`.
+    Authentication,
+    Io(io::Error),
+    /// Invalid client configuration or request.
+    Config(String),
+}
+
+impl CtError {
+    /// Error from an `<Error>` document with the status of the response.
+    pub fn service(status: u16, aws: AWSError) -> CtError {
+        CtError::Service {
+            status: Some(status),
+            code: aws.code,
+            message: aws.message,
+            request_id: aws.request_id,
+        }
+    }
+
+    /// Error from the body of a failed response, `<Error>` or `<ErrorResponse>`.
+    ///
+    /// A body without error document, e.g. of a `HEAD` request, gives an empty `code`.
+    pub fn from_response(status: u16, body: &str) -> CtError {
+        match ErrorDocument::from_body(body) {
+            Some(error) => CtError::Service {
+                status: Some(status),
+                code: error.code,
+                message: error.message,
+                request_id: error.request_id,
+            },
+            None => CtError::Service {
+                status: Some(status),
+                code: String::new(),
+                message: format!("HTTP {}", status),
+                request_id: String::new(),
+            },
+        }
+    }
+
+    /// Error `code` returned by OOS, e.g. `NoSuchKey`.
+    pub fn code(&self) -> Option<&str> {
+        match *self {
+            CtError::Service { ref code, .. } => Some(code),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for CtError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CtError::Service {
+                ref code,
+                ref message,
+                ref request_id,
+                ..
+            } => write!(f, "{}: {} (RequestId: {})", code, message, request_id),
+            CtError::Http(ref message) => write!(f, "HTTP error: {}", message),
+            CtError::Xml(ref message) => write!(f, "XML parse error: {}", message),
+            CtError::Credentials(ref message) => write!(f, "Credentials error: {}", message),
+            CtError::Crypto(ref message) => write!(f, "Crypto error: {}", message),
+            CtError::Authentication => write!(f, "Decryption failed, wrong password or method"),
+            CtError::Io(ref err) => write!(f, "IO error: {}", err),
+            CtError::Config(ref message) => write!(f, "Config error: {}", message),
+        }
+    }
+}
+
+impl Error for CtError {
+    fn description(&self) -> &str {
+        match *self {
+            CtError::Service { ref message, .. } => message,
+            CtError::Http(ref message) => message,
+            CtError::Xml(ref message) => message,
+            CtError::Credentials(ref message) => message,
+            CtError::Crypto(ref message) => message,
+            CtError::Authentication => "authentication failed",
+            CtError::Io(ref err) => err.description(),
+            CtError::Config(ref message) => message,
+        }
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        match *self {
+            CtError::Io(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// HTTP status of the documented S3 error codes, `None` for the other codes.
+pub fn status_of(code: &str) -> Option<u16> {
+    match code {
+        "BadDigest" | "EntityTooLarge" | "EntityTooSmall" | "ExpiredToken" | "IncompleteBody"
+        | "InvalidArgument" | "InvalidBucketName" | "InvalidDigest" | "InvalidPart"
+        | "InvalidPartOrder" | "InvalidRequest" | "InvalidToken" | "MalformedXML"
+        | "MalformedPolicy" | "RequestTimeout" | "TooManyBuckets" => Some(400),
+        "AccessDenied" | "AccountProblem" | "InvalidAccessKeyId" | "InvalidObjectState"
+        | "RequestTimeTooSkewed" | "SignatureDoesNotMatch" => Some(403),
+        "NoSuchBucket" | "NoSuchBucketPolicy" | "NoSuchCORSConfiguration" | "NoSuchKey"
+        | "NoSuchLifecycleConfiguration" | "NoSuchUpload" | "NoSuchVersion"
+        | "NoSuchWebsiteConfiguration" => Some(404),
+        "MethodNotAllowed" => Some(405),
+        "BucketAlreadyExists" | "BucketAlreadyOwnedByYou" | "BucketNotEmpty"
+        | "OperationAborted" => Some(409),
+        "MissingContentLength" => Some(411),
+        "PreconditionFailed" => Some(412),
+        "InvalidRange" => Some(416),
+        "InternalError" => Some(500),
+        "NotImplemented" => Some(501),
+        "ServiceUnavailable" | "SlowDown" => Some(503),
+        _ => None,
+    }
+}
+
+/// A `S3Error` without an error code comes from the transport. `S3Error` does not
+/// keep the HTTP status, it is inferred from the code.
+impl From<S3Error> for CtError {
+    fn from(err: S3Error) -> CtError {
+        if err.aws.code.is_empty() {
+            CtError::Http(err.message)
+        } else {
+            CtError::Service {
+                status: status_of(&err.aws.code),
+                code: err.aws.code,
+                message: err.aws.message,
+                request_id: err.aws.request_id,
+            }
+        }
+    }
+}
+
+impl From<XmlParseError> for CtError {
+    fn from(err: XmlParseError) -> CtError {
+        CtError::Xml(format!("{:?}", err))
+    }
+}
+
+impl From<CredentialsError> for CtError {
+    fn from(err: CredentialsError) -> CtError {
+        CtError::Credentials(err.message)
+    }
+}
+
+impl From<XmlError> for CtError {
+    fn from(err: XmlError) -> CtError {
+        CtError::Xml(format!("{}", err))
+    }
+}
+
+impl From<io::Error> for CtError {
+    fn from(err: io::Error) -> CtError {
+        CtError::Io(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_of_codes() {
+        assert_eq!(status_of("NoSuchKey"), Some(404));
+        assert_eq!(status_of("SlowDown"), Some(503));
+        assert_eq!(status_of("PreconditionFailed"), Some(412));
+        assert_eq!(status_of("QuotaExceeded"), None);
+    }
+}
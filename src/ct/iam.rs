@@ -21,26 +21,22 @@
 
 //! Additional API for IAM Operations
 //!
-use std::str;
-use std::str::FromStr;
 use std::fmt::{Display, Error, Formatter};
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration as StdDuration;
 
 use aws_sdk_rust::aws::common::signature::SignedRequest;
-use aws_sdk_rust::aws::common::signature::build_canonical_query_string;
 use aws_sdk_rust::aws::common::credentials::AwsCredentialsProvider;
-use aws_sdk_rust::aws::s3::s3client::sign_and_execute;
 
-use aws_sdk_rust::aws::common::xmlutil::*;
 use aws_sdk_rust::aws::common::common::*;
-use aws_sdk_rust::aws::common::params::ServiceParams;
-use aws_sdk_rust::aws::common::params::Params;
-use aws_sdk_rust::aws::errors::aws::AWSError;
 
-use aws_sdk_rust::aws::errors::s3::S3Error;
+use url::form_urlencoded;
 
 use ct::sdk::CTClient;
-
-use xml::EventReader;
+use ct::error::CtError;
+use ct::credentials::{CtProfileProvider, StaticProvider};
+use ct::decode::{decode, parse, Element, FromXml, XmlError};
 
 pub type IsPrimary = bool;
 
@@ -48,6 +44,15 @@ pub type AccessKeyId = String;
 
 pub type SecretAccessKey = String;
 
+pub type UserName = String;
+
+pub type PolicyName = String;
+
+pub type PolicyArn = String;
+
+/// Host of the IAM API
+const IAM_HOST: &'static str = "oos-bj2-iam.ctyunapi.cn";
+
 #[derive(Debug, Clone, RustcDecodable, RustcEncodable)]
 pub enum Status {
     Active,
@@ -69,6 +74,16 @@ impl Default for Status {
     }
 }
 
+impl FromXml for Status {
+    fn from_xml(element: &Element, path: &str) -> Result<Status, XmlError> {
+        match element.text.trim() {
+            "Active" | "active" => Ok(Status::Active),
+            "Inactive" | "inactive" => Ok(Status::Inactive),
+            text => Err(XmlError::new(path, &format!("invalid status {:?}", text))),
+        }
+    }
+}
+
 /// `AccessKeyMetadata` used for `Contents` for ListAccessKeyOutput
 #[derive(Debug, Default, RustcDecodable, RustcEncodable)]
 pub struct AccessKeyMetadata {
@@ -78,17 +93,34 @@ pub struct AccessKeyMetadata {
     pub is_primary: bool,
 }
 
+xml_struct! {
+    AccessKeyMetadata {
+        "UserName" => user_name,
+        "AccessKeyId" => access_key_id,
+        "Status" => status,
+        "IsPrimary" => is_primary,
+    }
+}
+
 /// `AccessKeyMetadataList` used for `AccessKeyMetadata` for ListAccessKeyOutput
 #[derive(Debug, Default, RustcDecodable, RustcEncodable)]
 pub struct AccessKeyMetadataList {
     pub member: Vec<AccessKeyMetadata>,
 }
 
+xml_struct! {
+    AccessKeyMetadataList {
+        "member" => [member],
+    }
+}
+
 //#[derive(Debug, Default)]
 #[derive(Debug, Default, Clone, RustcDecodable, RustcEncodable)]
 pub struct ListAccessKeyRequest {
     pub max_items: Option<String>,
     pub marker: Option<String>,
+    /// Keys of a sub-user
+    pub user_name: Option<String>,
 }
 
 /// Default output of all admin functions
@@ -100,505 +132,848 @@ pub struct ListAccessKeyOutput {
     pub marker: String,
 }
 
+xml_struct! {
+    ListAccessKeyOutput {
+        "UserName" => user_name,
+        "AccessKeyMetadata" => access_key_metadata,
+        "IsTruncated" => is_truncated,
+        "Marker" => marker,
+    }
+}
+
+/// Create an access key, for a sub-user when `user_name` is set
+#[derive(Debug, Default, Clone, RustcDecodable, RustcEncodable)]
+pub struct CreateAccessKeyRequest {
+    /// Create the primary key directly, a regular one by default (`IsPrimary=false`)
+    pub is_primary: bool,
+    pub user_name: Option<UserName>,
+}
+
+/// Default output of all admin functions
+#[derive(Debug, Default, Clone, RustcDecodable, RustcEncodable)]
+pub struct CreateAccessKeyOutput {
+    /// status code from the restful server
+    pub status: Status,
+    pub user_name: String,
+    pub access_key_id: AccessKeyId,
+    pub secret_access_key: SecretAccessKey,
+    pub is_primary: bool,
+}
+
+xml_struct! {
+    CreateAccessKeyOutput {
+        "Status" => status,
+        "UserName" => user_name,
+        "AccessKeyId" => access_key_id,
+        "SecretAccessKey" => secret_access_key,
+        "IsPrimary" => is_primary,
+    }
+}
+
+//#[derive(Debug, Default)]
+#[derive(Debug, Default, RustcDecodable, RustcEncodable)]
+pub struct DeleteAccessKeyRequest {
+    pub access_key_id: AccessKeyId,
+    /// Key of a sub-user
+    pub user_name: Option<String>,
+}
 
-/// Parse `String` from XML
-pub struct StringParser;
+/// Metadata of an IAM action without result
+#[derive(Debug, Default, Clone, RustcDecodable, RustcEncodable)]
+pub struct ResponseMetadata {
+    pub request_id: String,
+}
 
-impl StringParser {
-    pub fn parse_xml<T: Peek + Next>(
-        tag_name: &str,
-        stack: &mut T,
-    ) -> Result<AccessKeyId, XmlParseError> {
-        try!(start_element(tag_name, stack));
-        let obj = try!(characters(stack));
-        try!(end_element(tag_name, stack));
-        Ok(obj)
+xml_struct! {
+    ResponseMetadata {
+        "RequestId" => request_id,
     }
 }
 
-/// Parse `Bool` from XML
-pub struct BoolParser;
+pub type DeleteAccessKeyOutput = ResponseMetadata;
 
-impl BoolParser {
-    pub fn parse_xml<T: Peek + Next>(
-        tag_name: &str,
-        stack: &mut T,
-    ) -> Result<IsPrimary, XmlParseError> {
-        try!(start_element(tag_name, stack));
+//#[derive(Debug, Default)]
+#[derive(Debug, Default, RustcDecodable, RustcEncodable)]
+pub struct UpdateAccessKeyRequest {
+    pub access_key_id: AccessKeyId,
+    pub status: Option<Status>,
+    pub is_primary: Option<bool>,
+    /// Key of a sub-user
+    pub user_name: Option<String>,
+}
 
-        let mut obj = IsPrimary::default();
+pub type UpdateAccessKeyOutput = ResponseMetadata;
 
-        match characters(stack) {
-            Err(_) => return Ok(obj),
-            Ok(ref chars) => obj = bool::from_str(chars).unwrap(),
-        }
+/// A sub-user of the account
+#[derive(Debug, Default, Clone, RustcDecodable, RustcEncodable)]
+pub struct User {
+    pub user_name: UserName,
+    pub user_id: String,
+    pub arn: String,
+    pub create_date: String,
+}
 
-        try!(end_element(tag_name, stack));
-        Ok(obj)
+xml_struct! {
+    User {
+        "UserName" => user_name,
+        "UserId" => user_id,
+        "Arn" => arn,
+        "CreateDate" => create_date,
     }
 }
 
-/// Parse `Marker` from XML
-pub type MarkerParser = StringParser;
+#[derive(Debug, Default, Clone, RustcDecodable, RustcEncodable)]
+pub struct CreateUserRequest {
+    pub user_name: UserName,
+}
 
-/// Parse `AccessKeyId` from XML
-type AccessKeyIdParser = StringParser;
+#[derive(Debug, Default, Clone, RustcDecodable, RustcEncodable)]
+pub struct CreateUserOutput {
+    pub user: User,
+}
 
-/// Parse `SecretAccessKey` from XML
-type SecretAccessKeyParser = StringParser;
+xml_struct! {
+    CreateUserOutput {
+        "User" => user,
+    }
+}
 
-/// Parse `Status` from XML
-pub struct StatusParser;
+#[derive(Debug, Default, Clone, RustcDecodable, RustcEncodable)]
+pub struct ListUsersRequest {
+    pub max_items: Option<String>,
+    pub marker: Option<String>,
+}
 
-/// Parse `IsPrimary` from XML
-pub type IsPrimaryParser = BoolParser;
+#[derive(Debug, Default, Clone, RustcDecodable, RustcEncodable)]
+pub struct ListUsersOutput {
+    pub users: Vec<User>,
+    pub is_truncated: bool,
+    pub marker: String,
+}
 
-/// Parse `AccessKeyMetadataList` from XML
-pub struct AccessKeyMetadataListParser;
+xml_struct! {
+    ListUsersOutput {
+        "Users" => users,
+        "IsTruncated" => is_truncated,
+        "Marker" => marker,
+    }
+}
 
-/// Parse `AccessKeyMetadata` from XML
-pub struct AccessKeyMetadataParser;
+#[derive(Debug, Default, Clone, RustcDecodable, RustcEncodable)]
+pub struct DeleteUserRequest {
+    pub user_name: UserName,
+}
 
-/// Parse `ListAccessKeyOutput` from XML
-pub struct ListAccessKeyOutputParser;
+pub type DeleteUserOutput = ResponseMetadata;
 
-impl StatusParser {
-    pub fn parse_xml<T: Peek + Next>(
-        tag_name: &str,
-        stack: &mut T,
-    ) -> Result<Status, XmlParseError> {
-        try!(start_element(tag_name, stack));
-        let obj = try!(characters(stack));
-        try!(end_element(tag_name, stack));
-        if obj == "Active" {
-            Ok(Status::Active)
-        } else {
-            Ok(Status::Inactive)
-        }
+/// A managed policy of the account
+#[derive(Debug, Default, Clone, RustcDecodable, RustcEncodable)]
+pub struct Policy {
+    pub policy_name: PolicyName,
+    pub policy_id: String,
+    pub arn: PolicyArn,
+    pub description: String,
+    pub attachment_count: u32,
+    pub create_date: String,
+}
+
+xml_struct! {
+    Policy {
+        "PolicyName" => policy_name,
+        "PolicyId" => policy_id,
+        "Arn" => arn,
+        "Description" => description,
+        "AttachmentCount" => attachment_count,
+        "CreateDate" => create_date,
     }
 }
 
-impl AccessKeyMetadataParser {
-    pub fn parse_xml<T: Peek + Next>(
-        tag_name: &str,
-        stack: &mut T,
-    ) -> Result<AccessKeyMetadata, XmlParseError> {
-        try!(start_element(tag_name, stack));
-        let mut obj = AccessKeyMetadata::default();
-        loop {
-            let current_name = try!(peek_at_name(stack));
-            if current_name == "UserName" {
-                obj.user_name = try!(DisplayNameParser::parse_xml("UserName", stack));
-                continue;
-            }
-            if current_name == "AccessKeyId" {
-                obj.access_key_id = try!(AccessKeyIdParser::parse_xml("AccessKeyId", stack));
-                continue;
-            }
-            if current_name == "Status" {
-                obj.status = try!(StatusParser::parse_xml("Status", stack));
-                continue;
-            }
-            if current_name == "IsPrimary" {
-                obj.is_primary = try!(IsPrimaryParser::parse_xml("IsPrimary", stack));
-                continue;
-            }
-            break;
-        }
+#[derive(Debug, Default, Clone, RustcDecodable, RustcEncodable)]
+pub struct CreatePolicyRequest {
+    pub policy_name: PolicyName,
+    /// JSON policy document
+    pub policy_document: String,
+    pub description: Option<String>,
+}
 
-        try!(end_element(tag_name, stack));
-        Ok(obj)
+#[derive(Debug, Default, Clone, RustcDecodable, RustcEncodable)]
+pub struct CreatePolicyOutput {
+    pub policy: Policy,
+}
+
+xml_struct! {
+    CreatePolicyOutput {
+        "Policy" => policy,
     }
 }
 
-impl AccessKeyMetadataListParser {
-    pub fn parse_xml<T: Peek + Next>(
-        tag_name: &str,
-        stack: &mut T,
-    ) -> Result<AccessKeyMetadataList, XmlParseError> {
-        let mut obj = Vec::new();
+#[derive(Debug, Default, Clone, RustcDecodable, RustcEncodable)]
+pub struct ListPoliciesRequest {
+    pub max_items: Option<String>,
+    pub marker: Option<String>,
+}
 
-        while try!(peek_at_name(stack)) == tag_name {
-            obj.push(try!(AccessKeyMetadataParser::parse_xml(tag_name, stack)));
-        }
+#[derive(Debug, Default, Clone, RustcDecodable, RustcEncodable)]
+pub struct ListPoliciesOutput {
+    pub policies: Vec<Policy>,
+    pub is_truncated: bool,
+    pub marker: String,
+}
 
-        Ok(AccessKeyMetadataList { member: obj })
+xml_struct! {
+    ListPoliciesOutput {
+        "Policies" => policies,
+        "IsTruncated" => is_truncated,
+        "Marker" => marker,
     }
 }
 
-impl ListAccessKeyOutputParser {
-    pub fn parse_xml<T: Peek + Next>(
-        tag_name: &str,
-        stack: &mut T,
-    ) -> Result<ListAccessKeyOutput, XmlParseError> {
-        try!(start_element(tag_name, stack));
-        let mut obj = ListAccessKeyOutput::default();
-        loop {
-            let current_name = try!(peek_at_name(stack));
-            if current_name == "UserName" {
-                obj.user_name = try!(DisplayNameParser::parse_xml("UserName", stack));
-                continue;
-            }
-            if current_name == "AccessKeyMetadata" {
-                stack.next(); // skip AccessKeyMetadata start and go to contents
-                obj.access_key_metadata =
-                    try!(AccessKeyMetadataListParser::parse_xml("member", stack));
-                stack.next();
-                continue;
-            }
-            if current_name == "IsTruncated" {
-                obj.is_truncated = try!(IsTruncatedParser::parse_xml("IsTruncated", stack));
-                continue;
-            }
-            if current_name == "Marker" {
-                obj.marker = try!(MarkerParser::parse_xml("Marker", stack));
-                continue;
-            }
-            break;
-        }
-        try!(end_element(tag_name, stack));
-        Ok(obj)
+#[derive(Debug, Default, Clone, RustcDecodable, RustcEncodable)]
+pub struct DeletePolicyRequest {
+    pub policy_arn: PolicyArn,
+}
+
+pub type DeletePolicyOutput = ResponseMetadata;
+
+/// Used by `AttachUserPolicy` and `DetachUserPolicy`
+#[derive(Debug, Default, Clone, RustcDecodable, RustcEncodable)]
+pub struct UserPolicyRequest {
+    pub user_name: UserName,
+    pub policy_arn: PolicyArn,
+}
+
+pub type UserPolicyOutput = ResponseMetadata;
+
+/// A policy attached to a user
+#[derive(Debug, Default, Clone, RustcDecodable, RustcEncodable)]
+pub struct AttachedPolicy {
+    pub policy_name: PolicyName,
+    pub policy_arn: PolicyArn,
+}
+
+xml_struct! {
+    AttachedPolicy {
+        "PolicyName" => policy_name,
+        "PolicyArn" => policy_arn,
     }
 }
 
-/// Default output of all admin functions
 #[derive(Debug, Default, Clone, RustcDecodable, RustcEncodable)]
-pub struct CreateAccessKeyOutput {
-    /// status code from the restful server
-    pub status: Status,
-    pub user_name: String,
-    pub access_key_id: AccessKeyId,
-    pub secret_access_key: SecretAccessKey,
-    pub is_primary: bool,
+pub struct ListAttachedUserPoliciesRequest {
+    pub user_name: UserName,
+    pub max_items: Option<String>,
+    pub marker: Option<String>,
 }
 
+#[derive(Debug, Default, Clone, RustcDecodable, RustcEncodable)]
+pub struct ListAttachedUserPoliciesOutput {
+    pub attached_policies: Vec<AttachedPolicy>,
+    pub is_truncated: bool,
+    pub marker: String,
+}
 
-/// Parse `CreateAccessKeyOutput` from XML
-pub struct CreateAccessKeyOutputParser;
+xml_struct! {
+    ListAttachedUserPoliciesOutput {
+        "AttachedPolicies" => attached_policies,
+        "IsTruncated" => is_truncated,
+        "Marker" => marker,
+    }
+}
 
-impl CreateAccessKeyOutputParser {
-    pub fn parse_xml<T: Peek + Next>(
-        tag_name: &str,
-        stack: &mut T,
-    ) -> Result<CreateAccessKeyOutput, XmlParseError> {
-        try!(start_element(tag_name, stack));
-        let mut obj = CreateAccessKeyOutput::default();
-        stack.next(); // skip AccessKey start and go to contents
-        loop {
-            let current_name = try!(peek_at_name(stack));
-            if current_name == "UserName" {
-                obj.user_name = try!(DisplayNameParser::parse_xml("UserName", stack));
-                continue;
-            }
-            if current_name == "AccessKeyId" {
-                obj.access_key_id = try!(AccessKeyIdParser::parse_xml("AccessKeyId", stack));
-                continue;
+/// Form of an IAM action, sent as an `application/x-www-form-urlencoded` POST body.
+///
+/// Every value is percent-encoded, so names, markers and policy documents may
+/// contain `&`, `=` or non-ASCII characters.
+///
+/// ```
+/// let out: ListUsersOutput = try!(IamRequest::new("ListUsers")
+///     .param_opt("MaxItems", Some(100))
+///     .send(&ct, true, &["ListUsersResult"]));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct IamRequest {
+    params: Vec<(String, String)>,
+}
+
+impl IamRequest {
+    pub fn new(action: &str) -> IamRequest {
+        IamRequest { params: vec![(String::from("Action"), action.to_string())] }
+    }
+
+    /// Add a parameter.
+    pub fn param<V: ToString>(mut self, name: &str, value: V) -> IamRequest {
+        self.params.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Add a parameter if `value` is set.
+    pub fn param_opt<V: ToString>(self, name: &str, value: Option<V>) -> IamRequest {
+        match value {
+            Some(value) => self.param(name, value),
+            None => self,
+        }
+    }
+
+    /// The encoded body
+    pub fn body(&self) -> String {
+        let mut serializer = form_urlencoded::Serializer::new(String::new());
+        for &(ref name, ref value) in &self.params {
+            serializer.append_pair(name, value);
+        }
+        serializer.finish()
+    }
+
+    /// Sign and send the action, then decode the element at `path` of the response.
+    pub fn send<T: FromXml>(
+        &self,
+        ct: &CTClient,
+        idempotent: bool,
+        path: &[&str],
+    ) -> Result<T, CtError> {
+        self.send_to(ct, IAM_HOST, idempotent, path)
+    }
+
+    /// Like `send`, for the management APIs of another `host`.
+    pub fn send_to<T: FromXml>(
+        &self,
+        ct: &CTClient,
+        host: &str,
+        idempotent: bool,
+        path: &[&str],
+    ) -> Result<T, CtError> {
+        let body = try!(self.call(ct, host, idempotent));
+        Ok(try!(decode(&body, path)))
+    }
+
+    /// Send an action without result, its `ResponseMetadata` may be missing.
+    pub fn send_empty(&self, ct: &CTClient, idempotent: bool) -> Result<ResponseMetadata, CtError> {
+        let body = try!(self.call(ct, IAM_HOST, idempotent));
+        Ok(try!(response_metadata(&body)))
+    }
+
+    /// Sign and send the action, returning the body of a `200` response.
+    fn call(&self, ct: &CTClient, host: &str, idempotent: bool) -> Result<String, CtError> {
+        let payload = self.body().into_bytes();
+        let mut request = SignedRequest::new("POST", "s3", ct.region(), "", "/", ct.endpoint());
+        request.set_hostname(Some(host.to_string()));
+        request.update_header("Content-Type", "application/x-www-form-urlencoded");
+        request.set_payload(Some(&payload));
+
+        let result = try!(ct.sign_and_dispatch(&mut request, idempotent));
+        match result.status {
+            200 => Ok(result.body),
+            status => Err(CtError::from_response(status, &result.body)),
+        }
+    }
+}
+
+/// `CreateAccessKey` with an explicit `IsPrimary`, `false` unless a primary key is asked for.
+fn create_access_key_request(input: &CreateAccessKeyRequest) -> IamRequest {
+    IamRequest::new("CreateAccessKey")
+        .param("IsPrimary", input.is_primary)
+        .param_opt("UserName", input.user_name.as_ref())
+}
+
+/// The `ResponseMetadata` of a response without result, the default when it is missing.
+fn response_metadata(body: &str) -> Result<ResponseMetadata, XmlError> {
+    if body.trim().is_empty() {
+        return Ok(ResponseMetadata::default());
+    }
+    let root = try!(parse(body));
+    match root.child("ResponseMetadata") {
+        Some(metadata) => {
+            ResponseMetadata::from_xml(metadata, &format!("{}/ResponseMetadata", root.name))
+        }
+        None => Ok(ResponseMetadata::default()),
+    }
+}
+
+/// A listing request whose output is paged with `Marker` and `IsTruncated`
+pub trait Paged: Clone {
+    type Output;
+    type Item;
+
+    /// Request a page.
+    fn list(&self, client: &CTClient) -> Result<Self::Output, CtError>;
+    /// The marker of the page to request.
+    fn marker(&mut self) -> &mut Option<String>;
+    /// The marker of the next page, `None` on the last page.
+    fn next_marker(output: &Self::Output) -> Option<&String>;
+    /// The items of a page.
+    fn items(output: Self::Output) -> Vec<Self::Item>;
+}
+
+macro_rules! paged {
+    ($request:ident => $output:ident, $list:ident, $item:ty, $($items:ident).+) => {
+        impl Paged for $request {
+            type Output = $output;
+            type Item = $item;
+
+            fn list(&self, client: &CTClient) -> Result<$output, CtError> {
+                client.$list(self)
             }
-            if current_name == "SecretAccessKey" {
-                obj.secret_access_key =
-                    try!(SecretAccessKeyParser::parse_xml("SecretAccessKey", stack));
-                continue;
+
+            fn marker(&mut self) -> &mut Option<String> {
+                &mut self.marker
             }
-            if current_name == "Status" {
-                obj.status = try!(StatusParser::parse_xml("Status", stack));
-                continue;
+
+            fn next_marker(output: &$output) -> Option<&String> {
+                match output.is_truncated && !output.marker.is_empty() {
+                    true => Some(&output.marker),
+                    false => None,
+                }
             }
-            if current_name == "IsPrimary" {
-                obj.is_primary = try!(IsPrimaryParser::parse_xml("IsPrimary", stack));
-                continue;
+
+            fn items(output: $output) -> Vec<$item> {
+                output.$($items).+
             }
-            break;
         }
-        stack.next();
-        try!(end_element(tag_name, stack));
-        Ok(obj)
-    }
+    };
 }
 
-//#[derive(Debug, Default)]
-#[derive(Debug, Default, RustcDecodable, RustcEncodable)]
-pub struct DeleteAccessKeyRequest {
-    pub access_key_id: AccessKeyId,
+paged!(ListAccessKeyRequest => ListAccessKeyOutput, list_access_key, AccessKeyMetadata,
+       access_key_metadata.member);
+paged!(ListUsersRequest => ListUsersOutput, list_users, User, users);
+paged!(ListPoliciesRequest => ListPoliciesOutput, list_policies, Policy, policies);
+paged!(ListAttachedUserPoliciesRequest => ListAttachedUserPoliciesOutput,
+       list_attached_user_policies, AttachedPolicy, attached_policies);
+
+/// Iterator over the pages of a listing, following `Marker` while `IsTruncated`.
+///
+/// `max_items` of the request is the size of each page.
+pub struct ListPages<'a, R: Paged> {
+    client: &'a CTClient,
+    request: R,
+    done: bool,
 }
 
-/// Default output of all admin functions
-#[derive(Debug, Default, Clone, RustcDecodable, RustcEncodable)]
-pub struct DeleteAccessKeyOutput {
-    pub request_id: String,
+impl<'a, R: Paged> ListPages<'a, R> {
+    pub fn new(client: &'a CTClient, request: &R) -> ListPages<'a, R> {
+        ListPages {
+            client: client,
+            request: request.clone(),
+            done: false,
+        }
+    }
 }
 
-/// Parse `DeleteAccessKeyOutput` from XML
-pub struct DeleteAccessKeyOutputParser;
+impl<'a, R: Paged> Iterator for ListPages<'a, R> {
+    type Item = Result<R::Output, CtError>;
 
-impl DeleteAccessKeyOutputParser {
-    pub fn parse_xml<T: Peek + Next>(
-        tag_name: &str,
-        stack: &mut T,
-    ) -> Result<DeleteAccessKeyOutput, XmlParseError> {
-        try!(start_element(tag_name, stack));
-        let mut obj = DeleteAccessKeyOutput::default();
-        loop {
-            let current_name = try!(peek_at_name(stack));
-            if current_name == "RequestId" {
-                obj.request_id = try!(RequestIdParser::parse_xml("RequestId", stack));
-                continue;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let page = self.request.list(self.client);
+        match page.as_ref().ok().and_then(R::next_marker) {
+            Some(marker) => {
+                // Stop rather than loop forever on a server repeating its marker
+                if self.request.marker().as_ref() == Some(marker) {
+                    self.done = true;
+                }
+                *self.request.marker() = Some(marker.clone());
             }
-            break;
+            None => self.done = true,
         }
-        try!(end_element(tag_name, stack));
-        Ok(obj)
+        Some(page)
     }
 }
 
-//#[derive(Debug, Default)]
-#[derive(Debug, Default, RustcDecodable, RustcEncodable)]
-pub struct UpdateAccessKeyRequest {
-    pub access_key_id: AccessKeyId,
-    pub status: Option<Status>,
-    pub is_primary: Option<bool>,
+/// Iterator over the items of all the pages of a listing.
+///
+/// An error ends the iteration.
+pub struct ListIter<'a, R: Paged> {
+    pages: ListPages<'a, R>,
+    items: ::std::vec::IntoIter<R::Item>,
 }
 
-/// Default output of all admin functions
-#[derive(Debug, Default, Clone, RustcDecodable, RustcEncodable)]
-pub struct UpdateAccessKeyOutput {
-    pub request_id: String,
+impl<'a, R: Paged> ListIter<'a, R> {
+    pub fn new(client: &'a CTClient, request: &R) -> ListIter<'a, R> {
+        ListIter {
+            pages: ListPages::new(client, request),
+            items: Vec::new().into_iter(),
+        }
+    }
 }
 
+impl<'a, R: Paged> Iterator for ListIter<'a, R> {
+    type Item = Result<R::Item, CtError>;
 
-/// Parse `UpdateAccessKeyOutput` from XML
-pub struct UpdateAccessKeyOutputParser;
-
-impl UpdateAccessKeyOutputParser {
-    pub fn parse_xml<T: Peek + Next>(
-        tag_name: &str,
-        stack: &mut T,
-    ) -> Result<UpdateAccessKeyOutput, XmlParseError> {
-        try!(start_element(tag_name, stack));
-        let mut obj = UpdateAccessKeyOutput::default();
+    fn next(&mut self) -> Option<Self::Item> {
         loop {
-            let current_name = try!(peek_at_name(stack));
-            if current_name == "RequestId" {
-                obj.request_id = try!(RequestIdParser::parse_xml("RequestId", stack));
-                continue;
+            if let Some(item) = self.items.next() {
+                return Some(Ok(item));
+            }
+            match self.pages.next() {
+                Some(Ok(page)) => self.items = R::items(page).into_iter(),
+                Some(Err(err)) => return Some(Err(err)),
+                None => return None,
             }
-            break;
         }
-        try!(end_element(tag_name, stack));
-        Ok(obj)
     }
 }
 
+/// Pages of `ListAccessKey`
+pub type ListAccessKeyPages<'a> = ListPages<'a, ListAccessKeyRequest>;
+/// Keys of all the pages of `ListAccessKey`
+pub type ListAccessKeyIter<'a> = ListIter<'a, ListAccessKeyRequest>;
+
+/// Options of `rotate_access_key`
+#[derive(Debug, Default, Clone)]
+pub struct RotateAccessKeyRequest {
+    /// The key being replaced
+    pub access_key_id: AccessKeyId,
+    /// Owner of the key when it belongs to a sub-user
+    pub user_name: Option<UserName>,
+    /// Profile of the credentials file to store the new key in
+    pub profile: Option<CtProfileProvider>,
+    /// Delete the old key after this grace period, it is only deactivated when `None`
+    pub delete_after: Option<StdDuration>,
+}
+
+/// Output of `rotate_access_key`
+#[derive(Debug, Default, Clone)]
+pub struct RotateAccessKeyOutput {
+    pub access_key: CreateAccessKeyOutput,
+    pub old_access_key_id: AccessKeyId,
+    /// Path of the credentials file updated
+    pub saved_to: Option<PathBuf>,
+    pub deleted: bool,
+}
+
+/// Attempts to use a new key, which may not be effective at once.
+const VERIFY_ATTEMPTS: u32 = 5;
+
 /// A trait to additional iam operating for CTClient.
 pub trait CTClientIAM {
     /// List all keys.
     fn list_access_key(&self, input: &ListAccessKeyRequest)
-        -> Result<ListAccessKeyOutput, S3Error>;
+        -> Result<ListAccessKeyOutput, CtError>;
+    /// List the pages of keys, see `ListAccessKeyPages`.
+    fn list_access_key_pages(&self, input: &ListAccessKeyRequest) -> ListAccessKeyPages;
+    /// List the keys of all pages.
+    ///
+    /// ```
+    /// for key in ct.list_access_key_iter(&ListAccessKeyRequest::default()) {
+    ///     println!("{:?}", try!(key));
+    /// }
+    /// ```
+    fn list_access_key_iter(&self, input: &ListAccessKeyRequest) -> ListAccessKeyIter;
     /// Create a new key.
-    fn create_access_key(&self) -> Result<CreateAccessKeyOutput, S3Error>;
+    fn create_access_key(&self) -> Result<CreateAccessKeyOutput, CtError>;
+    /// Create a new key, e.g. for a sub-user.
+    fn create_access_key_for(
+        &self,
+        input: &CreateAccessKeyRequest,
+    ) -> Result<CreateAccessKeyOutput, CtError>;
     /// Deletes a given key.
     fn delete_access_key(
         &self,
         input: &DeleteAccessKeyRequest,
-    ) -> Result<DeleteAccessKeyOutput, S3Error>;
+    ) -> Result<DeleteAccessKeyOutput, CtError>;
     /// Set the status of key.
     fn update_access_key(
         &self,
         input: &UpdateAccessKeyRequest,
-    ) -> Result<UpdateAccessKeyOutput, S3Error>;
+    ) -> Result<UpdateAccessKeyOutput, CtError>;
+    /// Replace a key by a new one.
+    ///
+    /// 1. create a new key, primary if the old one is;
+    /// 2. check that the new key works, or delete it and fail;
+    /// 3. store it in `input.profile`;
+    /// 4. deactivate the old key;
+    /// 5. delete the old key after `input.delete_after`.
+    ///
+    /// The old key is deleted with the new one when the client uses the old key.
+    /// The key of a sub-user is rotated with `input.user_name`.
+    fn rotate_access_key(
+        &self,
+        input: &RotateAccessKeyRequest,
+    ) -> Result<RotateAccessKeyOutput, CtError>;
+    /// Create a sub-user.
+    fn create_user(&self, input: &CreateUserRequest) -> Result<CreateUserOutput, CtError>;
+    /// List the sub-users.
+    fn list_users(&self, input: &ListUsersRequest) -> Result<ListUsersOutput, CtError>;
+    /// List the sub-users of all pages.
+    fn list_users_iter(&self, input: &ListUsersRequest) -> ListIter<ListUsersRequest>;
+    /// Delete a sub-user, its keys and policies must be removed first.
+    fn delete_user(&self, input: &DeleteUserRequest) -> Result<DeleteUserOutput, CtError>;
+    /// Create a policy from a JSON document.
+    fn create_policy(&self, input: &CreatePolicyRequest) -> Result<CreatePolicyOutput, CtError>;
+    /// List the policies.
+    fn list_policies(&self, input: &ListPoliciesRequest) -> Result<ListPoliciesOutput, CtError>;
+    /// List the policies of all pages.
+    fn list_policies_iter(&self, input: &ListPoliciesRequest) -> ListIter<ListPoliciesRequest>;
+    /// Delete a policy, it must be detached first.
+    fn delete_policy(&self, input: &DeletePolicyRequest) -> Result<DeletePolicyOutput, CtError>;
+    /// Grant a policy to a sub-user.
+    fn attach_user_policy(&self, input: &UserPolicyRequest) -> Result<UserPolicyOutput, CtError>;
+    /// Revoke a policy of a sub-user.
+    fn detach_user_policy(&self, input: &UserPolicyRequest) -> Result<UserPolicyOutput, CtError>;
+    /// List the policies of a sub-user.
+    fn list_attached_user_policies(
+        &self,
+        input: &ListAttachedUserPoliciesRequest,
+    ) -> Result<ListAttachedUserPoliciesOutput, CtError>;
+    /// List the policies of a sub-user of all pages.
+    fn list_attached_user_policies_iter(
+        &self,
+        input: &ListAttachedUserPoliciesRequest,
+    ) -> ListIter<ListAttachedUserPoliciesRequest>;
 }
 
 impl CTClientIAM for CTClient {
     fn list_access_key(
         &self,
         input: &ListAccessKeyRequest,
-    ) -> Result<ListAccessKeyOutput, S3Error> {
-        let input = input.clone();
-        let payload: Vec<u8>;
-        let mut request = SignedRequest::new("POST", "s3", self.region(), "", "/", self.endpoint());
-
-        request.set_hostname(Some(String::from("oos-bj2-iam.ctyunapi.cn")));
-
-        let mut params = Params::new();
-
-        params.put("Action", "ListAccessKey");
-
-        if let Some(max_items) = input.max_items {
-            params.put("MaxItems", &max_items);
-        }
-
-        if let Some(marker) = input.marker {
-            params.put("Marker", &marker);
-        }
-
-        let body = build_canonical_query_string(&params);
-
-        payload = body.into_bytes();
-        request.set_payload(Some(&payload));
-
-        let result = sign_and_execute(
-            &self.dispatcher,
-            &mut request,
-            try!(self.credentials_provider.credentials()),
-        );
-
-        let status = result.status;
-        let reader = EventReader::from_str(&result.body);
-        let mut stack = XmlResponse::new(reader.into_iter().peekable());
+    ) -> Result<ListAccessKeyOutput, CtError> {
+        IamRequest::new("ListAccessKey")
+            .param_opt("MaxItems", input.max_items.as_ref())
+            .param_opt("Marker", input.marker.as_ref())
+            .param_opt("UserName", input.user_name.as_ref())
+            .send(self, true, &["ListAccessKeysResult"])
+    }
 
-        stack.next(); // xml start tag
+    fn list_access_key_pages(&self, input: &ListAccessKeyRequest) -> ListAccessKeyPages {
+        ListPages::new(self, input)
+    }
 
-        match status {
-            200 => {
-                stack.next(); // ListAccessKeysResponse
-                Ok((ListAccessKeyOutputParser::parse_xml("ListAccessKeysResult", &mut stack))?)
-            }
-            _ => {
-                let aws = try!(AWSError::parse_xml("Error", &mut stack));
-                Err(S3Error::with_aws("Error listing access keys", aws))
-            }
-        }
+    fn list_access_key_iter(&self, input: &ListAccessKeyRequest) -> ListAccessKeyIter {
+        ListIter::new(self, input)
     }
 
     /// 创建一组 AK/SK
-    fn create_access_key(&self) -> Result<CreateAccessKeyOutput, S3Error> {
-        let mut request = SignedRequest::new("POST", "s3", self.region(), "", "/", self.endpoint());
-
-        request.set_hostname(Some(String::from("oos-bj2-iam.ctyunapi.cn")));
-
-        request.set_payload(Some("Action=CreateAccessKey&IsPrimary=false".as_bytes()));
-
-        let result = sign_and_execute(
-            &self.dispatcher,
-            &mut request,
-            try!(self.credentials_provider.credentials()),
-        );
-
-        let status = result.status;
-        let reader = EventReader::from_str(&result.body);
-        let mut stack = XmlResponse::new(reader.into_iter().peekable());
-
-        stack.next(); // xml start tag
+    fn create_access_key(&self) -> Result<CreateAccessKeyOutput, CtError> {
+        self.create_access_key_for(&CreateAccessKeyRequest::default())
+    }
 
-        match status {
-            200 => {
-                stack.next(); // CreateAccessKeyResponse
-                Ok((CreateAccessKeyOutputParser::parse_xml("CreateAccessKeyResult", &mut stack))?)
-            }
-            _ => {
-                let aws = try!(AWSError::parse_xml("Error", &mut stack));
-                Err(S3Error::with_aws("Error listing access keys", aws))
-            }
-        }
+    fn create_access_key_for(
+        &self,
+        input: &CreateAccessKeyRequest,
+    ) -> Result<CreateAccessKeyOutput, CtError> {
+        create_access_key_request(input).send(self, false, &["CreateAccessKeyResult", "AccessKey"])
     }
 
     /// 删除已有的 AK/SK
     fn delete_access_key(
         &self,
         input: &DeleteAccessKeyRequest,
-    ) -> Result<DeleteAccessKeyOutput, S3Error> {
-        let payload: Vec<u8>;
-        let mut request = SignedRequest::new("POST", "s3", self.region(), "", "/", self.endpoint());
-
-        request.set_hostname(Some(String::from("oos-bj2-iam.ctyunapi.cn")));
+    ) -> Result<DeleteAccessKeyOutput, CtError> {
+        IamRequest::new("DeleteAccessKey")
+            .param("AccessKeyId", &input.access_key_id)
+            .param_opt("UserName", input.user_name.as_ref())
+            .send_empty(self, true)
+    }
 
-        let body = format!(
-            "Action=DeleteAccessKey&AccessKeyId={}",
-            &input.access_key_id
-        );
-        payload = body.into_bytes();
-        request.set_payload(Some(&payload));
+    /// 更改 AK/SK属性（主秘钥/普通秘钥）
+    fn update_access_key(
+        &self,
+        input: &UpdateAccessKeyRequest,
+    ) -> Result<UpdateAccessKeyOutput, CtError> {
+        IamRequest::new("UpdateAccessKey")
+            .param("AccessKeyId", &input.access_key_id)
+            .param_opt("Status", input.status.as_ref())
+            .param_opt("IsPrimary", input.is_primary)
+            .param_opt("UserName", input.user_name.as_ref())
+            .send_empty(self, true)
+    }
 
-        let result = sign_and_execute(
-            &self.dispatcher,
-            &mut request,
-            try!(self.credentials_provider.credentials()),
+    fn rotate_access_key(
+        &self,
+        input: &RotateAccessKeyRequest,
+    ) -> Result<RotateAccessKeyOutput, CtError> {
+        let mut old = None;
+        for key in self.list_access_key_iter(&ListAccessKeyRequest {
+            user_name: input.user_name.clone(),
+            ..Default::default()
+        }) {
+            let key = try!(key);
+            if key.access_key_id == input.access_key_id {
+                old = Some(key);
+                break;
+            }
+        }
+        let old = match old {
+            Some(old) => old,
+            None => {
+                return Err(CtError::Config(format!(
+                    "access key {} not found",
+                    input.access_key_id
+                )))
+            }
+        };
+
+        let mut access_key = try!(self.create_access_key_for(&CreateAccessKeyRequest {
+            is_primary: false,
+            user_name: input.user_name.clone(),
+        }));
+        info!("Created access key {}", access_key.access_key_id);
+
+        let rotated = CTClient::with_dispatcher(
+            StaticProvider::new(&access_key.access_key_id, &access_key.secret_access_key),
+            self.dispatcher.clone(),
+            None,
+            None,
         );
 
-        let status = result.status;
-        let reader = EventReader::from_str(&result.body);
-        let mut stack = XmlResponse::new(reader.into_iter().peekable());
+        let mut attempt = 1;
+        while let Err(err) = rotated.list_buckets().map_err(CtError::from) {
+            if attempt >= VERIFY_ATTEMPTS {
+                warn!("New access key {} rejected: {}", access_key.access_key_id, err);
+                if let Err(cleanup) = self.delete_access_key(&DeleteAccessKeyRequest {
+                    access_key_id: access_key.access_key_id.clone(),
+                    user_name: input.user_name.clone(),
+                }) {
+                    error!(
+                        "New access key {} could not be deleted: {}",
+                        access_key.access_key_id,
+                        cleanup
+                    );
+                }
+                return Err(err);
+            }
+            thread::sleep(StdDuration::from_secs(1 << attempt));
+            attempt += 1;
+        }
 
-        stack.next(); // xml start tag
+        // Once the old key is inactive, only the new key can act for its owner
+        let own_key = try!(self.credentials_provider().credentials()).aws_access_key_id() ==
+            old.access_key_id.as_str();
+        let manager = if own_key { &rotated } else { self };
+
+        if old.is_primary {
+            try!(self.update_access_key(&UpdateAccessKeyRequest {
+                access_key_id: access_key.access_key_id.clone(),
+                status: Some(Status::Active),
+                is_primary: Some(true),
+                user_name: input.user_name.clone(),
+            }));
+            access_key.is_primary = true;
+        }
 
-        match status {
-            200 => {
-                stack.next(); // DeleteAccessKeyResponse
-                Ok((DeleteAccessKeyOutputParser::parse_xml("ResponseMetadata", &mut stack))?)
+        let saved_to = match input.profile {
+            Some(ref profile) => {
+                try!(profile.save(&access_key.access_key_id, &access_key.secret_access_key));
+                Some(profile.path().to_path_buf())
             }
-            _ => {
-                let aws = try!(AWSError::parse_xml("Error", &mut stack));
-                Err(S3Error::with_aws("Error listing access keys", aws))
+            None => None,
+        };
+
+        try!(self.update_access_key(&UpdateAccessKeyRequest {
+            access_key_id: old.access_key_id.clone(),
+            status: Some(Status::Inactive),
+            is_primary: Some(false),
+            user_name: input.user_name.clone(),
+        }));
+
+        let deleted = match input.delete_after {
+            Some(grace) => {
+                thread::sleep(grace);
+                try!(manager.delete_access_key(&DeleteAccessKeyRequest {
+                    access_key_id: old.access_key_id.clone(),
+                    user_name: input.user_name.clone(),
+                }));
+                true
             }
-        }
+            None => false,
+        };
+
+        Ok(RotateAccessKeyOutput {
+            access_key: access_key,
+            old_access_key_id: old.access_key_id,
+            saved_to: saved_to,
+            deleted: deleted,
+        })
     }
 
-    /// 更改 AK/SK属性（主秘钥/普通秘钥）
-    fn update_access_key(
-        &self,
-        input: &UpdateAccessKeyRequest,
-    ) -> Result<UpdateAccessKeyOutput, S3Error> {
-        let payload: Vec<u8>;
-        let mut request = SignedRequest::new("POST", "s3", self.region(), "", "/", self.endpoint());
-
-        request.set_hostname(Some(String::from("oos-bj2-iam.ctyunapi.cn")));
+    fn create_user(&self, input: &CreateUserRequest) -> Result<CreateUserOutput, CtError> {
+        IamRequest::new("CreateUser")
+            .param("UserName", &input.user_name)
+            .send(self, false, &["CreateUserResult"])
+    }
 
-        let mut params = Params::new();
+    fn list_users(&self, input: &ListUsersRequest) -> Result<ListUsersOutput, CtError> {
+        IamRequest::new("ListUsers")
+            .param_opt("MaxItems", input.max_items.as_ref())
+            .param_opt("Marker", input.marker.as_ref())
+            .send(self, true, &["ListUsersResult"])
+    }
 
-        params.put("Action", "UpdateAccessKey");
-        params.put("AccessKeyId", &input.access_key_id);
+    fn list_users_iter(&self, input: &ListUsersRequest) -> ListIter<ListUsersRequest> {
+        ListIter::new(self, input)
+    }
 
-        if let Some(ref status) = input.status {
-            params.put("Status", &status.to_string());
-        }
+    fn delete_user(&self, input: &DeleteUserRequest) -> Result<DeleteUserOutput, CtError> {
+        IamRequest::new("DeleteUser")
+            .param("UserName", &input.user_name)
+            .send_empty(self, true)
+    }
 
-        if let Some(ref is_primary) = input.is_primary {
-            params.put("IsPrimary", &format!("{}", is_primary));
-        }
+    fn create_policy(&self, input: &CreatePolicyRequest) -> Result<CreatePolicyOutput, CtError> {
+        IamRequest::new("CreatePolicy")
+            .param("PolicyName", &input.policy_name)
+            .param("PolicyDocument", &input.policy_document)
+            .param_opt("Description", input.description.as_ref())
+            .send(self, false, &["CreatePolicyResult"])
+    }
 
-        let body = build_canonical_query_string(&params);
+    fn list_policies(&self, input: &ListPoliciesRequest) -> Result<ListPoliciesOutput, CtError> {
+        IamRequest::new("ListPolicies")
+            .param_opt("MaxItems", input.max_items.as_ref())
+            .param_opt("Marker", input.marker.as_ref())
+            .send(self, true, &["ListPoliciesResult"])
+    }
 
-        payload = body.into_bytes();
-        request.set_payload(Some(&payload));
+    fn list_policies_iter(&self, input: &ListPoliciesRequest) -> ListIter<ListPoliciesRequest> {
+        ListIter::new(self, input)
+    }
 
-        let result = sign_and_execute(
-            &self.dispatcher,
-            &mut request,
-            try!(self.credentials_provider.credentials()),
-        );
+    fn delete_policy(&self, input: &DeletePolicyRequest) -> Result<DeletePolicyOutput, CtError> {
+        IamRequest::new("DeletePolicy")
+            .param("PolicyArn", &input.policy_arn)
+            .send_empty(self, true)
+    }
 
+    fn attach_user_policy(&self, input: &UserPolicyRequest) -> Result<UserPolicyOutput, CtError> {
+        IamRequest::new("AttachUserPolicy")
+            .param("UserName", &input.user_name)
+            .param("PolicyArn", &input.policy_arn)
+            .send_empty(self, true)
+    }
 
-        let status = result.status;
+    fn detach_user_policy(&self, input: &UserPolicyRequest) -> Result<UserPolicyOutput, CtError> {
+        IamRequest::new("DetachUserPolicy")
+            .param("UserName", &input.user_name)
+            .param("PolicyArn", &input.policy_arn)
+            .send_empty(self, true)
+    }
 
-        // println!("{:?}", result);
-        let reader = EventReader::from_str(&result.body);
-        let mut stack = XmlResponse::new(reader.into_iter().peekable());
-        stack.next(); // xml start tag
+    fn list_attached_user_policies(
+        &self,
+        input: &ListAttachedUserPoliciesRequest,
+    ) -> Result<ListAttachedUserPoliciesOutput, CtError> {
+        IamRequest::new("ListAttachedUserPolicies")
+            .param("UserName", &input.user_name)
+            .param_opt("MaxItems", input.max_items.as_ref())
+            .param_opt("Marker", input.marker.as_ref())
+            .send(self, true, &["ListAttachedUserPoliciesResult"])
+    }
 
-        match status {
-            200 => {
-                // stack.next(); // UpdateAccessKeyResponse
-                // Ok(try!(UpdateAccessKeyOutputParser::parse_xml("ResponseMetadata", &mut stack)))
-                Ok(UpdateAccessKeyOutput {
-                    request_id: result.headers.get("x-amz-request-id").unwrap().to_string(),
-                })
-            }
-            _ => {
-                let aws = try!(AWSError::parse_xml("Error", &mut stack));
-                Err(S3Error::with_aws("Error listing access keys", aws))
-            }
-        }
+    fn list_attached_user_policies_iter(
+        &self,
+        input: &ListAttachedUserPoliciesRequest,
+    ) -> ListIter<ListAttachedUserPoliciesRequest> {
+        ListIter::new(self, input)
     }
 }
 
-
 #[cfg(test)]
 mod tests {
     use aws_sdk_rust::aws::common::credentials::*;
     use aws_sdk_rust::aws::s3::s3client::S3Client;
 
     use super::super::sdk::CTClient;
-    use super::CTClientIAM;
-    use ct::iam::ListAccessKeyRequest;
+    use super::*;
 
     #[test]
     fn list() {
@@ -612,4 +987,92 @@ mod tests {
             Err(err) => println!("{:?}", err),
         }
     }
+
+    #[test]
+    fn parse_users_and_policies() {
+        let body = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ListUsersResponse>
+  <ListUsersResult>
+    <Users>
+      <member>
+        <UserName>alice</UserName>
+        <UserId>AIDA1</UserId>
+        <Arn>arn:ctyun:iam::1:user/alice</Arn>
+        <CreateDate>2017-12-01T00:00:00Z</CreateDate>
+      </member>
+      <member>
+        <UserName>bob</UserName>
+      </member>
+    </Users>
+    <IsTruncated>true</IsTruncated>
+    <Marker>bob</Marker>
+  </ListUsersResult>
+</ListUsersResponse>"#;
+        let out: ListUsersOutput = decode(body, &["ListUsersResult"]).unwrap();
+        assert_eq!(out.users.len(), 2);
+        assert_eq!(out.users[0].arn, "arn:ctyun:iam::1:user/alice");
+        assert_eq!(out.users[1].user_name, "bob");
+        assert!(out.is_truncated);
+        assert_eq!(out.marker, "bob");
+        assert_eq!(ListUsersRequest::next_marker(&out), Some(&String::from("bob")));
+        let last = ListUsersOutput {
+            marker: String::from("bob"),
+            ..Default::default()
+        };
+        assert_eq!(ListUsersRequest::next_marker(&last), None);
+        assert_eq!(ListUsersRequest::items(out).len(), 2);
+
+        let body = r#"<?xml version="1.0" encoding="UTF-8"?>
+<CreatePolicyResponse>
+  <CreatePolicyResult>
+    <Policy>
+      <PolicyName>read</PolicyName>
+      <DefaultVersionId>v1</DefaultVersionId>
+      <Arn>arn:ctyun:iam::1:policy/read</Arn>
+      <AttachmentCount>2</AttachmentCount>
+    </Policy>
+  </CreatePolicyResult>
+</CreatePolicyResponse>"#;
+        let out: CreatePolicyOutput = decode(body, &["CreatePolicyResult"]).unwrap();
+        assert_eq!(out.policy.policy_name, "read");
+        assert_eq!(out.policy.arn, "arn:ctyun:iam::1:policy/read");
+        assert_eq!(out.policy.attachment_count, 2);
+    }
+
+    #[test]
+    fn missing_response_metadata() {
+        let body = "<DeleteAccessKeyResponse><ResponseMetadata>\
+                    <RequestId>7a62c49f-347e-4fc4-9331-6e8eEXAMPLE</RequestId>\
+                    </ResponseMetadata></DeleteAccessKeyResponse>";
+        let metadata = response_metadata(body).unwrap();
+        assert_eq!(metadata.request_id, "7a62c49f-347e-4fc4-9331-6e8eEXAMPLE");
+
+        let metadata = response_metadata("<DeleteAccessKeyResponse/>").unwrap();
+        assert_eq!(metadata.request_id, "");
+        assert_eq!(response_metadata("").unwrap().request_id, "");
+    }
+
+    #[test]
+    fn create_access_key_body() {
+        let request = CreateAccessKeyRequest::default();
+        assert_eq!(
+            create_access_key_request(&request).body(),
+            "Action=CreateAccessKey&IsPrimary=false"
+        );
+    }
+
+    #[test]
+    fn form_body() {
+        let form = IamRequest::new("CreatePolicy")
+            .param("PolicyName", "a&b=c d")
+            .param("PolicyDocument", "{\"Version\":\"2012-10-17\"}")
+            .param_opt("Description", Some("只读"))
+            .param_opt::<bool>("IsPrimary", None);
+        assert_eq!(
+            form.body(),
+            "Action=CreatePolicy&PolicyName=a%26b%3Dc+d\
+             &PolicyDocument=%7B%22Version%22%3A%222012-10-17%22%7D\
+             &Description=%E5%8F%AA%E8%AF%BB"
+        );
+    }
 }
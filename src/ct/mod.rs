@@ -19,10 +19,21 @@
 // CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION
 // WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 
+#[macro_use]
+pub mod decode;
 pub mod sdk;
+pub mod error;
+pub mod credentials;
+pub mod dispatcher;
 pub mod bucket;
+pub mod policy;
+pub mod acl;
+pub mod access_log;
 pub mod object;
 pub mod iam;
+pub mod stats;
+#[cfg(feature = "async")]
+pub mod async_client;
 mod crypto;
 mod crypto_io;
 
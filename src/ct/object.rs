@@ -21,12 +21,13 @@
 
 //! Additional API for Object Operations
 use md5::{Digest, Md5};
+use std::cmp;
+use std::fmt;
 use std::iter::repeat;
-use std::io::Write;
-use openssl::sign::Signer;
-use openssl::hash::MessageDigest;
-use openssl::pkey::PKey;
-use rustc_serialize::json;
+use std::io::Read;
+use std::collections::BTreeMap;
+use rand::{thread_rng, Rng};
+use rustc_serialize::json::Json;
 use rustc_serialize::base64::{ToBase64, STANDARD};
 
 use aws_sdk_rust::aws::common::signature::SignedRequest;
@@ -34,14 +35,21 @@ pub use aws_sdk_rust::aws::common::credentials::AwsCredentialsProvider;
 
 use aws_sdk_rust::aws::s3::bucket::*;
 pub use aws_sdk_rust::aws::s3::object::*;
-use aws_sdk_rust::aws::errors::s3::S3Error;
+pub use aws_sdk_rust::aws::s3::acl::CannedAcl;
+use aws_sdk_rust::aws::s3::acl::canned_acl_in_aws_format;
+use aws_sdk_rust::aws::common::xmlutil::*;
+use aws_sdk_rust::aws::errors::aws::AWSError;
 
-use hyper::Server;
-use hyper::server::{Request, Response};
-use chrono::{self, UTC, Duration};
+use chrono::{DateTime, Duration, UTC};
+use url::Url;
+use xml::EventReader;
 
 use ct::sdk::CTClient;
 use ct::sdk::CTSignedRequest;
+use ct::sdk::hmac_sha1;
+use ct::error::CtError;
+use ct::decode::{decode, parse, ErrorDocument, FromXml, XmlError};
+use ct::bucket::push_element;
 use ct::crypto_io::encrypt_payload;
 use ct::crypto_io::decrypt_payload;
 use ct::crypto_io::CipherType;
@@ -64,13 +72,45 @@ pub trait CTClientObject {
     ///     Err(err) => println!("{:#?}", err),
     /// }
     /// ```
-    fn presigned_object(&self, input: &PresignedObjectRequest) -> Result<String, S3Error>;
+    fn presigned_object(&self, input: &PresignedObjectRequest) -> Result<String, CtError>;
 
-    fn post_object(&self, input: &PostObjectRequest) -> Result<PostObjectOutput, S3Error>;
+    /// Sign a `PostPolicy`, the returned fields make up a browser upload form.
+    ///
+    /// ```
+    /// let policy = PostPolicy::new("bucket").key("hello.txt");
+    /// match s3.post_object(&policy) {
+    ///     Ok(out) => println!("{:#?}", out),
+    ///     Err(err) => println!("{:#?}", err),
+    /// }
+    /// ```
+    fn post_object(&self, policy: &PostPolicy) -> Result<PostObjectOutput, CtError>;
+
+    /// Upload `body` through the POST Object API with a form signed by `post_object`.
+    ///
+    /// ```
+    /// let policy = PostPolicy::new("bucket")
+    ///     .key_prefix("uploads/")
+    ///     .content_type_prefix("text/");
+    /// let form = s3.post_object(&policy)?;
+    /// match s3.post_object_upload(&PostObjectUploadRequest {
+    ///     form: &form,
+    ///     key: None,
+    ///     file_name: "hello.txt".to_string(),
+    ///     content_type: Some("text/plain".to_string()),
+    /// }, File::open("hello.txt")?) {
+    ///     Ok(out) => println!("{:#?}", out),
+    ///     Err(err) => println!("{:#?}", err),
+    /// }
+    /// ```
+    fn post_object_upload<R: Read>(
+        &self,
+        input: &PostObjectUploadRequest,
+        body: R,
+    ) -> Result<PostObjectUploadOutput, CtError>;
 }
 
 impl CTClientObject for CTClient {
-    fn presigned_object(&self, input: &PresignedObjectRequest) -> Result<String, S3Error> {
+    fn presigned_object(&self, input: &PresignedObjectRequest) -> Result<String, CtError> {
         let mut request = SignedRequest::new(
             "GET",
             "s3",
@@ -85,10 +125,8 @@ impl CTClientObject for CTClient {
 
         request.set_hostname(Some(hostname));
 
-        let (date, signature) = request.presigned(
-            &self.credentials_provider().credentials().unwrap(),
-            &input.expires,
-        );
+        let credentials = try!(self.credentials_provider().credentials());
+        let (date, signature) = request.presigned(&credentials, &input.expires);
 
         request.remove_header("authorization");
         request.remove_header("content-length");
@@ -100,13 +138,7 @@ impl CTClientObject for CTClient {
 
         request.add_header_raw("Signature", signature.as_ref());
         request.add_header_raw("Expires", date.as_ref());
-        request.add_header_raw(
-            "AWSAccessKeyId",
-            &self.credentials_provider()
-                .credentials()
-                .unwrap()
-                .aws_access_key_id(),
-        );
+        request.add_header_raw("AWSAccessKeyId", credentials.aws_access_key_id());
 
 
         let url = request.gen_url();
@@ -114,87 +146,429 @@ impl CTClientObject for CTClient {
         Ok(url)
     }
 
-    fn post_object(&self, input: &PostObjectRequest) -> Result<PostObjectOutput, S3Error> {
-        let date_str = UTC::now() + match input.expires {
-            Some(ref h) => Duration::days(h.parse::<i64>().unwrap()),
-            None => Duration::days(1),
+    fn post_object(&self, policy: &PostPolicy) -> Result<PostObjectOutput, CtError> {
+        let credentials = try!(self.credentials_provider().credentials());
+
+        let scheme = match self.endpoint().endpoint {
+            Some(ref url) => url.scheme().to_string(),
+            None => "http".to_string(),
         };
+        let url = format!("{}://{}", scheme, self.hostname(Some(&policy.bucket)));
 
-        let url;
-        let key;
-        let aws_access_key_id;
-        let policy;
-        let signature;
+        let document = policy.to_json(&(UTC::now() + policy.expires));
+        debug!("Post Policy: {}", document);
 
-        {
+        let encoded = document.as_bytes().to_base64(STANDARD);
+        let signature = hmac_sha1(credentials.aws_secret_access_key(), encoded.as_bytes());
+
+        let mut fields = Vec::new();
+        fields.push(("key".to_string(), policy.key_field()));
+        fields.push((
+            "AWSAccessKeyId".to_string(),
+            credentials.aws_access_key_id().to_string(),
+        ));
+        fields.push(("Policy".to_string(), encoded));
+        fields.push(("Signature".to_string(), signature));
 
-            url = format!("{}://{}", "http", self.hostname(Some(&input.bucket)));
-            key = &input.key;
-            {
+        if let Some(ref acl) = policy.acl {
+            fields.push(("acl".to_string(), canned_acl_in_aws_format(acl)));
+        }
+        if let Some(ref prefix) = policy.content_type_prefix {
+            // Placeholder satisfying the condition, the uploader should replace it.
+            fields.push(("Content-Type".to_string(), prefix.clone()));
+        }
+        if let Some(ref redirect) = policy.success_action_redirect {
+            fields.push(("success_action_redirect".to_string(), redirect.clone()));
+        }
+        if let Some(status) = policy.success_action_status {
+            fields.push(("success_action_status".to_string(), status.to_string()));
+        }
+        for (name, value) in &policy.metadata {
+            fields.push((format!("x-amz-meta-{}", name), value.clone()));
+        }
+
+        Ok(PostObjectOutput {
+            url: url,
+            fields: fields,
+        })
+    }
+
+    fn post_object_upload<R: Read>(
+        &self,
+        input: &PostObjectUploadRequest,
+        mut body: R,
+    ) -> Result<PostObjectUploadOutput, CtError> {
+        let has_content_type = input
+            .form
+            .fields
+            .iter()
+            .any(|&(ref name, _)| name == "Content-Type");
+        if input.content_type.is_some() && !has_content_type {
+            // A field without condition is rejected by the policy check
+            return Err(CtError::Config(String::from(
+                "content type needs a form signed with a content type prefix",
+            )));
+        }
+
+        let mut content = Vec::new();
+        if let Err(err) = body.read_to_end(&mut content) {
+            return Err(CtError::Io(err));
+        }
+
+        let boundary = format!(
+            "----CTFormBoundary{}",
+            thread_rng().gen_ascii_chars().take(16).collect::<String>()
+        );
+        let payload = multipart_form(&boundary, input, &content);
+
+        // Sent through the dispatcher of the client, its proxy, CA and timeouts, but not
+        // signed: the form carries its own signature.
+        let url = match Url::parse(&input.form.url) {
+            Ok(url) => url,
+            Err(err) => return Err(CtError::Config(format!("invalid form url: {}", err))),
+        };
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            (None, _) => return Err(CtError::Config(format!("invalid form url {}", url))),
+        };
+        let mut request =
+            SignedRequest::new("POST", "s3", self.region(), "", url.path(), self.endpoint());
+        request.set_hostname(Some(host));
+        request.update_header(
+            "Content-Type",
+            &format!("multipart/form-data; boundary={}", boundary),
+        );
+        request.set_payload(Some(&payload));
+
+        let response = match self.dispatcher.dispatch_with(&request, false) {
+            Ok(response) => response,
+            Err(err) => return Err(CtError::Http(format!("{}", err))),
+        };
+        let result = &response.body;
+        debug!("{} {}", response.status, result);
+
+        let header = |name: &str| -> Option<String> {
+            response
+                .headers
+                .iter()
+                .find(|&(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.to_string())
+        };
 
-                aws_access_key_id = self.credentials_provider().credentials().unwrap().aws_access_key_id().to_string();
+        let reader = EventReader::from_str(result);
+        let mut stack = XmlResponse::new(reader.into_iter().peekable());
+        stack.next(); // xml start tag
+
+        match response.status {
+            201 => Ok(try!(PostObjectUploadOutputParser::parse_xml(
+                "PostResponse",
+                &mut stack
+            ))),
+            200 | 204 => Ok(PostObjectUploadOutput {
+                location: header("Location"),
+                e_tag: header("ETag").unwrap_or_default(),
+                ..Default::default()
+            }),
+            301 | 302 | 303 | 307 => Ok(PostObjectUploadOutput {
+                redirect: header("Location"),
+                ..Default::default()
+            }),
+            _ => {
+                let aws = try!(AWSError::parse_xml("Error", &mut stack));
+                Err(CtError::service(response.status, aws))
             }
+        }
+    }
+}
+
+/// Build the `multipart/form-data` payload, the `file` field has to be the last one.
+fn multipart_form(boundary: &str, input: &PostObjectUploadRequest, content: &[u8]) -> Vec<u8> {
+    let mut payload = Vec::with_capacity(content.len() + 1024);
 
-            signature = {
-                let hmac_pkey = PKey::hmac(self.credentials_provider.credentials().unwrap().aws_secret_access_key().as_bytes()).unwrap();
-                let mut hmac = Signer::new(MessageDigest::sha1(), &hmac_pkey).unwrap();
-
-                policy = format!(r#"
-                    {{
-                        "expiration":"{:?}",
-                        "conditions":[
-                            {{"bucket": "{}"}},
-                            ["starts-with","$key",""]
-                        ]
-                    }}
-                "#, date_str, input.bucket).as_bytes().to_base64(STANDARD);
-                let _ = hmac.write_all(&policy.clone().into_bytes());
-                hmac.finish().unwrap().to_base64(STANDARD)
-            };
-        }
-
-
-        let body = format!(r#"
-                <html>
-                  <head>
-                    <meta http-equiv="Content-Type" content="text/html; charset=UTF-8" />
-                  </head>
-                  <body>
-                  <form action="{}" method="post" enctype="multipart/form-data">
-                    Key: <input type="input" name="key" value="{}" />
-                    <br/>
-                    <input type="hidden" name="AWSAccessKeyId" value="{}" />
-                    <input type="hidden" name="Policy" value="{}" />
-                    <input type="hidden" name="Signature" value="{}" />
-                    File: <input type="file" name="file"/>
-                    <br/>
-                    <input type="submit" name="submit" value="Upload" />
-                  </form>
-                </html>
-            "#, &url, &key, &aws_access_key_id, &policy, &signature).to_string();
-
-        let _listening = Server::http("127.0.0.1:3000").unwrap()
-            .handle(move |_: Request, res: Response|res.send(body.as_bytes()).unwrap());
-        println!("Open http://127.0.0.1:3000 in browser for upload file");
-        Ok(PostObjectOutput{})
+    for &(ref name, ref value) in &input.form.fields {
+        let value = match (name.as_str(), &input.key, &input.content_type) {
+            ("key", &Some(ref key), _) => key,
+            ("Content-Type", _, &Some(ref content_type)) => content_type,
+            _ => value,
+        };
+        payload.extend_from_slice(
+            format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n",
+                boundary, name, value
+            ).as_bytes(),
+        );
     }
 
+    payload.extend_from_slice(
+        format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{}\"\r\n",
+            boundary,
+            input.file_name.replace('"', "")
+        ).as_bytes(),
+    );
+    payload.extend_from_slice(
+        format!(
+            "Content-Type: {}\r\n\r\n",
+            match input.content_type {
+                Some(ref content_type) => content_type.as_str(),
+                None => "application/octet-stream",
+            }
+        ).as_bytes(),
+    );
+    payload.extend_from_slice(content);
+    payload.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
 
+    payload
 }
 
 use aws_sdk_rust::aws::common::common::Operation;
 
-//#[derive(Debug, Default)]
+/// Condition on the `key` field of a browser-based upload.
+#[derive(Debug, Clone)]
+pub enum PostKey {
+    /// Only this exact key may be uploaded.
+    Exact(ObjectKey),
+    /// Any key starting with the prefix may be uploaded, the form uses `${filename}`.
+    StartsWith(String),
+}
+
+/// Builder of the policy document for browser-based upload (POST Object).
+///
+/// ```
+/// let policy = PostPolicy::new("bucket")
+///     .key_prefix("uploads/")
+///     .content_length_range(1, 100 * 1024 * 1024)
+///     .success_action_status(201);
+///
+/// match s3.post_object(&policy) {
+///     Ok(out) => println!("{:#?}", out.fields),
+///     Err(err) => println!("{:#?}", err),
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct PostPolicy {
+    pub bucket: BucketName,
+    pub key: Option<PostKey>,
+    /// How long the signed form stays valid, one day by default.
+    pub expires: Duration,
+    pub content_length_range: Option<(u64, u64)>,
+    pub content_type_prefix: Option<String>,
+    pub success_action_redirect: Option<String>,
+    pub success_action_status: Option<u16>,
+    pub acl: Option<CannedAcl>,
+    /// `x-amz-meta-*` fields, without the prefix.
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl PostPolicy {
+    pub fn new(bucket: &str) -> Self {
+        PostPolicy {
+            bucket: bucket.to_string(),
+            key: None,
+            expires: Duration::days(1),
+            content_length_range: None,
+            content_type_prefix: None,
+            success_action_redirect: None,
+            success_action_status: None,
+            acl: None,
+            metadata: BTreeMap::new(),
+        }
+    }
+
+    /// Restrict the upload to exactly `key`.
+    pub fn key(mut self, key: &str) -> Self {
+        self.key = Some(PostKey::Exact(key.to_string()));
+        self
+    }
+
+    /// Allow any key starting with `prefix`.
+    pub fn key_prefix(mut self, prefix: &str) -> Self {
+        self.key = Some(PostKey::StartsWith(prefix.to_string()));
+        self
+    }
+
+    pub fn expires(mut self, expires: Duration) -> Self {
+        self.expires = expires;
+        self
+    }
+
+    /// Minimum and maximum size of the uploaded file in bytes.
+    pub fn content_length_range(mut self, min: u64, max: u64) -> Self {
+        self.content_length_range = Some((min, max));
+        self
+    }
+
+    /// Required prefix of the `Content-Type` field, e.g. `image/`.
+    pub fn content_type_prefix(mut self, prefix: &str) -> Self {
+        self.content_type_prefix = Some(prefix.to_string());
+        self
+    }
+
+    pub fn success_action_redirect(mut self, url: &str) -> Self {
+        self.success_action_redirect = Some(url.to_string());
+        self
+    }
+
+    /// Status returned on success when no redirect is given (200, 201 or 204).
+    pub fn success_action_status(mut self, status: u16) -> Self {
+        self.success_action_status = Some(status);
+        self
+    }
+
+    pub fn acl(mut self, acl: CannedAcl) -> Self {
+        self.acl = Some(acl);
+        self
+    }
+
+    /// Add a `x-amz-meta-<name>` field with a fixed value.
+    pub fn meta(mut self, name: &str, value: &str) -> Self {
+        self.metadata.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /// Value of the `key` form field matching the key condition.
+    pub fn key_field(&self) -> String {
+        match self.key {
+            Some(PostKey::Exact(ref key)) => key.clone(),
+            Some(PostKey::StartsWith(ref prefix)) => format!("{}${{filename}}", prefix),
+            None => "${filename}".to_string(),
+        }
+    }
+
+    /// Policy document expiring at `expiration`, before base64 encoding.
+    pub fn to_json(&self, expiration: &DateTime<UTC>) -> String {
+        let mut conditions = Vec::new();
+
+        conditions.push(field_eq("bucket", &self.bucket));
+
+        conditions.push(match self.key {
+            Some(PostKey::Exact(ref key)) => condition("eq", "$key", key),
+            Some(PostKey::StartsWith(ref prefix)) => condition("starts-with", "$key", prefix),
+            None => condition("starts-with", "$key", ""),
+        });
+
+        if let Some(ref acl) = self.acl {
+            conditions.push(field_eq("acl", &canned_acl_in_aws_format(acl)));
+        }
+        if let Some((min, max)) = self.content_length_range {
+            conditions.push(Json::Array(vec![
+                Json::String("content-length-range".to_string()),
+                Json::U64(min),
+                Json::U64(max),
+            ]));
+        }
+        if let Some(ref prefix) = self.content_type_prefix {
+            conditions.push(condition("starts-with", "$Content-Type", prefix));
+        }
+        if let Some(ref redirect) = self.success_action_redirect {
+            conditions.push(field_eq("success_action_redirect", redirect));
+        }
+        if let Some(status) = self.success_action_status {
+            conditions.push(field_eq("success_action_status", &status.to_string()));
+        }
+        for (name, value) in &self.metadata {
+            conditions.push(field_eq(&format!("x-amz-meta-{}", name), value));
+        }
+
+        let mut document = BTreeMap::new();
+        document.insert(
+            "expiration".to_string(),
+            Json::String(expiration.format("%Y-%m-%dT%H:%M:%S.000Z").to_string()),
+        );
+        document.insert("conditions".to_string(), Json::Array(conditions));
+
+        Json::Object(document).to_string()
+    }
+}
+
+fn field_eq(name: &str, value: &str) -> Json {
+    let mut field = BTreeMap::new();
+    field.insert(name.to_string(), Json::String(value.to_string()));
+    Json::Object(field)
+}
+
+fn condition(op: &str, name: &str, value: &str) -> Json {
+    Json::Array(vec![
+        Json::String(op.to_string()),
+        Json::String(name.to_string()),
+        Json::String(value.to_string()),
+    ])
+}
+
+/// Signed form of a browser-based upload, `fields` go before the `file` field.
+#[derive(Debug, Default, Clone, RustcDecodable, RustcEncodable)]
+pub struct PostObjectOutput {
+    /// Form action
+    pub url: String,
+    pub fields: Vec<(String, String)>,
+}
+
+#[derive(Debug)]
+pub struct PostObjectUploadRequest<'a> {
+    pub form: &'a PostObjectOutput,
+    /// Replaces the `key` field of the form, e.g. instead of `${filename}`.
+    pub key: Option<ObjectKey>,
+    pub file_name: String,
+    /// `Content-Type` of the file, `application/octet-stream` by default.
+    ///
+    /// It replaces the `Content-Type` field, so the form must be signed with
+    /// `PostPolicy::content_type_prefix`.
+    pub content_type: Option<String>,
+}
+
+/// Response of a browser-based upload
 #[derive(Debug, Default, Clone, RustcDecodable, RustcEncodable)]
-pub struct PostObjectRequest {
+pub struct PostObjectUploadOutput {
+    pub location: Option<String>,
     pub bucket: BucketName,
     pub key: ObjectKey,
-    pub expires: Option<Expires>,
+    pub e_tag: String,
+    /// Target of `success_action_redirect`, with `bucket`, `key` and `etag` in the query.
+    pub redirect: Option<String>,
 }
 
-//#[derive(Debug, Default)]
-#[derive(Debug, Default, Clone, RustcDecodable, RustcEncodable)]
-pub struct PostObjectOutput {}
+/// Parse `PostObjectUploadOutput` from XML
+pub struct PostObjectUploadOutputParser;
+
+impl PostObjectUploadOutputParser {
+    pub fn parse_xml<T: Peek + Next>(
+        tag_name: &str,
+        stack: &mut T,
+    ) -> Result<PostObjectUploadOutput, XmlParseError> {
+        try!(start_element(tag_name, stack));
+        let mut obj = PostObjectUploadOutput::default();
+        loop {
+            let current_name = try!(peek_at_name(stack));
+            if current_name == "Location" {
+                obj.location = Some(try!(parse_string("Location", stack)));
+                continue;
+            }
+            if current_name == "Bucket" {
+                obj.bucket = try!(parse_string("Bucket", stack));
+                continue;
+            }
+            if current_name == "Key" {
+                obj.key = try!(parse_string("Key", stack));
+                continue;
+            }
+            if current_name == "ETag" {
+                obj.e_tag = try!(parse_string("ETag", stack));
+                continue;
+            }
+            break;
+        }
+        try!(end_element(tag_name, stack));
+        Ok(obj)
+    }
+}
+
+fn parse_string<T: Peek + Next>(tag_name: &str, stack: &mut T) -> Result<String, XmlParseError> {
+    try!(start_element(tag_name, stack));
+    let obj = try!(characters(stack));
+    try!(end_element(tag_name, stack));
+    Ok(obj)
+}
 
 /// A trait to additional securely for CTClient.
 pub trait CTClientEncryptionObject {
@@ -204,13 +578,13 @@ pub trait CTClientEncryptionObject {
         &self,
         input: PutObjectRequest,
         operation: Option<&mut Operation>,
-    ) -> Result<PutObjectOutput, S3Error>;
+    ) -> Result<PutObjectOutput, CtError>;
 
     fn get_object_securely(
         &self,
         input: &GetObjectRequest,
         operation: Option<&mut Operation>,
-    ) -> Result<GetObjectOutput, S3Error>;
+    ) -> Result<GetObjectOutput, CtError>;
 }
 
 impl CTClientEncryptionObject for CTClient {
@@ -218,7 +592,7 @@ impl CTClientEncryptionObject for CTClient {
         &self,
         input: PutObjectRequest,
         operation: Option<&mut Operation>,
-    ) -> Result<PutObjectOutput, S3Error> {
+    ) -> Result<PutObjectOutput, CtError> {
         let plaintext = input.body.unwrap();
         let cipherbody = match encrypt_payload(
             self.method(),
@@ -226,9 +600,7 @@ impl CTClientEncryptionObject for CTClient {
             input.body.unwrap(),
         ) {
             Ok(body) => body,
-            Err(err) => {
-                return Err(S3Error::default());
-            }
+            Err(err) => return Err(err),
         };
 
         let mut request = PutObjectRequest::from(input);
@@ -240,14 +612,14 @@ impl CTClientEncryptionObject for CTClient {
         let hash = sh.hash().to_base64(STANDARD);
         request.content_md5 = Some(hash);
 
-        self.put_object(&request, operation)
+        self.put_object(&request, operation).map_err(CtError::from)
     }
 
     fn get_object_securely(
         &self,
         input: &GetObjectRequest,
         operation: Option<&mut Operation>,
-    ) -> Result<GetObjectOutput, S3Error> {
+    ) -> Result<GetObjectOutput, CtError> {
         match self.get_object(input, operation) {
             Ok(out) => {
                 let mut output = GetObjectOutput::from(out);
@@ -261,9 +633,7 @@ impl CTClientEncryptionObject for CTClient {
                         &cipherbody,
                     ) {
                         Ok(body) => body,
-                        Err(err) => {
-                            return Err(S3Error::default());
-                        }
+                        Err(err) => return Err(err),
                     };
                 }
                 if output.is_body {
@@ -273,7 +643,822 @@ impl CTClientEncryptionObject for CTClient {
                 }
                 Ok(output)
             }
-            Err(err) => Err(err),
+            Err(err) => Err(CtError::from(err)),
+        }
+    }
+}
+
+/// Parameters of `list_object_versions`, the markers continue a truncated listing
+#[derive(Debug, Default, Clone)]
+pub struct ObjectVersionsRequest {
+    pub bucket: BucketName,
+    pub prefix: Option<String>,
+    pub delimiter: Option<String>,
+    pub key_marker: Option<String>,
+    pub version_id_marker: Option<String>,
+    pub max_keys: Option<u32>,
+}
+
+/// A version of an object
+#[derive(Debug, Default, Clone, RustcDecodable, RustcEncodable)]
+pub struct ObjectVersion {
+    pub key: String,
+    /// `null` for the objects written before versioning was enabled
+    pub version_id: String,
+    pub is_latest: bool,
+    pub last_modified: String,
+    pub e_tag: String,
+    pub size: u64,
+    pub storage_class: String,
+}
+
+xml_struct! {
+    ObjectVersion {
+        "Key" => key,
+        "VersionId" => version_id,
+        "IsLatest" => is_latest,
+        "LastModified" => last_modified,
+        "ETag" => e_tag,
+        "Size" => size,
+        "StorageClass" => storage_class,
+    }
+}
+
+/// Left by a delete without version id, the object looks deleted while it is the latest
+#[derive(Debug, Default, Clone, RustcDecodable, RustcEncodable)]
+pub struct DeleteMarker {
+    pub key: String,
+    pub version_id: String,
+    pub is_latest: bool,
+    pub last_modified: String,
+}
+
+xml_struct! {
+    DeleteMarker {
+        "Key" => key,
+        "VersionId" => version_id,
+        "IsLatest" => is_latest,
+        "LastModified" => last_modified,
+    }
+}
+
+/// `<Prefix>` of a `<CommonPrefixes>` element
+#[derive(Debug, Default, Clone, RustcDecodable, RustcEncodable)]
+pub struct CommonPrefix {
+    pub prefix: String,
+}
+
+xml_struct! {
+    CommonPrefix {
+        "Prefix" => prefix,
+    }
+}
+
+/// A page of `list_object_versions`, newest versions first for each key
+#[derive(Debug, Default, Clone, RustcDecodable, RustcEncodable)]
+pub struct ObjectVersionsOutput {
+    pub versions: Vec<ObjectVersion>,
+    pub delete_markers: Vec<DeleteMarker>,
+    pub common_prefixes: Vec<CommonPrefix>,
+    pub is_truncated: bool,
+    pub next_key_marker: Option<String>,
+    pub next_version_id_marker: Option<String>,
+}
+
+xml_struct! {
+    ObjectVersionsOutput {
+        "Version" => [versions],
+        "DeleteMarker" => [delete_markers],
+        "CommonPrefixes" => [common_prefixes],
+        "IsTruncated" => is_truncated,
+        "NextKeyMarker" => next_key_marker,
+        "NextVersionIdMarker" => next_version_id_marker,
+    }
+}
+
+/// A trait to additional object versions for CTClient.
+///
+/// Versions of an object are read and deleted by setting `version_id` of
+/// `GetObjectRequest`, `HeadObjectRequest` and `DeleteObjectRequest`.
+pub trait CTClientObjectVersion {
+    /// List a page of the versions and delete markers of a bucket.
+    fn list_object_versions(
+        &self,
+        input: &ObjectVersionsRequest,
+    ) -> Result<ObjectVersionsOutput, CtError>;
+
+    /// Make the version `version_id` of an object its latest version again by a
+    /// server-side copy, the newer versions are kept.
+    fn restore_object_version(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: &str,
+    ) -> Result<ObjectCopyOutput, CtError>;
+}
+
+impl CTClientObjectVersion for CTClient {
+    fn list_object_versions(
+        &self,
+        input: &ObjectVersionsRequest,
+    ) -> Result<ObjectVersionsOutput, CtError> {
+        let mut request =
+            SignedRequest::new("GET", "s3", self.region(), &input.bucket, "/", self.endpoint());
+        request.set_hostname(Some(self.hostname(Some(&input.bucket))));
+        request.add_param("versions", "");
+        if let Some(ref prefix) = input.prefix {
+            request.add_param("prefix", prefix);
+        }
+        if let Some(ref delimiter) = input.delimiter {
+            request.add_param("delimiter", delimiter);
+        }
+        if let Some(ref key_marker) = input.key_marker {
+            request.add_param("key-marker", key_marker);
+        }
+        if let Some(ref version_id_marker) = input.version_id_marker {
+            request.add_param("version-id-marker", version_id_marker);
+        }
+        if let Some(max_keys) = input.max_keys {
+            request.add_param("max-keys", &max_keys.to_string());
+        }
+
+        let result = try!(self.sign_and_dispatch(&mut request, true));
+        match result.status {
+            200 => Ok(try!(decode(&result.body, &[]))),
+            status => Err(CtError::from_response(status, &result.body)),
+        }
+    }
+
+    fn restore_object_version(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: &str,
+    ) -> Result<ObjectCopyOutput, CtError> {
+        let mut input = ObjectCopyRequest::new(bucket, key, bucket, key);
+        input.source_version_id = Some(version_id.to_string());
+        self.copy_object(&input)
+    }
+}
+
+/// Largest object copied by a single `PUT Object - Copy`, 5 GB
+pub const COPY_OBJECT_MAX_SIZE: u64 = 5 * 1024 * 1024 * 1024;
+
+/// Smallest part of a multipart copy, raised to stay within 10000 parts
+pub const COPY_PART_SIZE: u64 = 512 * 1024 * 1024;
+
+/// Whether a copy keeps the metadata of the source or replaces it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MetadataDirective {
+    Copy,
+    Replace,
+}
+
+impl Default for MetadataDirective {
+    fn default() -> Self {
+        MetadataDirective::Copy
+    }
+}
+
+impl fmt::Display for MetadataDirective {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &MetadataDirective::Copy => write!(f, "COPY"),
+            &MetadataDirective::Replace => write!(f, "REPLACE"),
+        }
+    }
+}
+
+/// Parameters of `copy_object`, the source and destination may be in different buckets
+#[derive(Debug, Default, Clone)]
+pub struct ObjectCopyRequest {
+    pub source_bucket: BucketName,
+    pub source_key: ObjectKey,
+    /// Copy an older version, the latest one by default
+    pub source_version_id: Option<String>,
+    pub bucket: BucketName,
+    pub key: ObjectKey,
+    /// `content_type` and `metadata` are only sent with `Replace`
+    pub metadata_directive: MetadataDirective,
+    pub content_type: Option<String>,
+    /// `x-amz-meta-*` headers, without the prefix
+    pub metadata: Vec<(String, String)>,
+    pub storage_class: Option<String>,
+    pub acl: Option<CannedAcl>,
+    /// Copy only if the ETag of the source matches
+    pub if_match: Option<String>,
+    /// Copy only if the ETag of the source differs
+    pub if_none_match: Option<String>,
+    /// HTTP date, e.g. `Wed, 22 Nov 2017 08:00:00 GMT`
+    pub if_modified_since: Option<String>,
+    pub if_unmodified_since: Option<String>,
+}
+
+impl ObjectCopyRequest {
+    pub fn new(source_bucket: &str, source_key: &str, bucket: &str, key: &str) -> Self {
+        ObjectCopyRequest {
+            source_bucket: source_bucket.to_string(),
+            source_key: source_key.to_string(),
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Value of the `x-amz-copy-source` header
+    pub fn copy_source(&self) -> String {
+        let mut source = format!("/{}/{}", self.source_bucket, encode_key(&self.source_key));
+        if let Some(ref version_id) = self.source_version_id {
+            source.push_str(&format!("?versionId={}", encode_key(version_id)));
+        }
+        source
+    }
+}
+
+/// Percent-encode everything but the unreserved characters and `/`.
+fn encode_key(key: &str) -> String {
+    key.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// Result of a copy, or of the completion of a multipart copy
+#[derive(Debug, Default, Clone, RustcDecodable, RustcEncodable)]
+pub struct ObjectCopyOutput {
+    pub e_tag: String,
+    pub last_modified: String,
+    /// ETag of the source which was copied
+    pub source_e_tag: Option<String>,
+    /// Version of the source which was copied, in a versioned bucket
+    pub source_version_id: Option<String>,
+}
+
+xml_struct! {
+    ObjectCopyOutput {
+        "ETag" => e_tag,
+        "LastModified" => last_modified,
+    }
+}
+
+#[derive(Debug, Default)]
+struct InitiateMultipartUploadResult {
+    upload_id: String,
+}
+
+xml_struct! {
+    InitiateMultipartUploadResult {
+        "UploadId" => upload_id,
+    }
+}
+
+/// Size and metadata of the source of a copy, read by a `HEAD` request
+#[derive(Debug, Default)]
+struct CopySource {
+    size: u64,
+    e_tag: Option<String>,
+    version_id: Option<String>,
+    content_type: Option<String>,
+    metadata: Vec<(String, String)>,
+}
+
+/// A trait to additional server-side copy for CTClient.
+pub trait CTClientObjectCopy {
+    /// Copy an object without downloading it, objects larger than
+    /// `COPY_OBJECT_MAX_SIZE` are copied by parts with `UploadPartCopy`.
+    ///
+    /// ```
+    /// let mut input = ObjectCopyRequest::new("photos", "2017/a.jpg", "backup", "a.jpg");
+    /// input.if_match = Some("\"fba9dede5f27731c9771645a39863328\"".to_string());
+    /// match s3.copy_object(&input) {
+    ///     Ok(out) => println!("{:#?}", out),
+    ///     Err(err) => println!("{:#?}", err),
+    /// }
+    /// ```
+    fn copy_object(&self, input: &ObjectCopyRequest) -> Result<ObjectCopyOutput, CtError>;
+
+    /// Copy an object by parts of `part_size` bytes, whatever its size.
+    fn copy_object_multipart(
+        &self,
+        input: &ObjectCopyRequest,
+        part_size: u64,
+    ) -> Result<ObjectCopyOutput, CtError>;
+
+    /// Copy an object, then delete the source which was copied: its version in a
+    /// versioned bucket, otherwise the source if its ETag is unchanged.
+    ///
+    /// In a versioned bucket the copied version is deleted permanently, no delete
+    /// marker is left. Moving an object onto itself is refused, unless a
+    /// `source_version_id` is given to restore that version.
+    fn move_object(&self, input: &ObjectCopyRequest) -> Result<ObjectCopyOutput, CtError>;
+}
+
+impl CTClientObjectCopy for CTClient {
+    fn copy_object(&self, input: &ObjectCopyRequest) -> Result<ObjectCopyOutput, CtError> {
+        let source = try!(head_source(self, input));
+        if source.size > COPY_OBJECT_MAX_SIZE {
+            let part_size = cmp::max(COPY_PART_SIZE, (source.size + 9999) / 10000);
+            return copy_parts(self, input, &source, part_size);
+        }
+
+        let path = format!("/{}", input.key);
+        let mut request =
+            SignedRequest::new("PUT", "s3", self.region(), &input.bucket, &path, self.endpoint());
+        request.set_hostname(Some(self.hostname(Some(&input.bucket))));
+        copy_source_headers(&mut request, input, &source);
+        request.update_header(
+            "x-amz-metadata-directive",
+            &input.metadata_directive.to_string(),
+        );
+        if input.metadata_directive == MetadataDirective::Replace {
+            object_headers(&mut request, &input.content_type, &input.metadata);
+        }
+        storage_headers(&mut request, input);
+
+        let result = try!(self.sign_and_dispatch(&mut request, true));
+        copy_result(result.status, &result.body, "CopyObjectResult")
+            .map(|output| copied(output, input, &source))
+    }
+
+    fn copy_object_multipart(
+        &self,
+        input: &ObjectCopyRequest,
+        part_size: u64,
+    ) -> Result<ObjectCopyOutput, CtError> {
+        let source = try!(head_source(self, input));
+        copy_parts(self, input, &source, part_size)
+    }
+
+    fn move_object(&self, input: &ObjectCopyRequest) -> Result<ObjectCopyOutput, CtError> {
+        if input.bucket == input.source_bucket && input.key == input.source_key
+            && input.source_version_id.is_none()
+        {
+            return Err(CtError::Config(format!("{} is moved to itself", input.key)));
+        }
+        let output = try!(self.copy_object(input));
+        let path = format!("/{}", input.source_key);
+        let mut request = SignedRequest::new(
+            "DELETE",
+            "s3",
+            self.region(),
+            &input.source_bucket,
+            &path,
+            self.endpoint(),
+        );
+        request.set_hostname(Some(self.hostname(Some(&input.source_bucket))));
+        match output.source_version_id {
+            Some(ref version_id) => request.add_param("versionId", version_id),
+            None => {
+                // No conditional delete, the source may still change after this check.
+                let source = try!(head_source(self, input));
+                if source.e_tag != output.source_e_tag {
+                    return Err(CtError::Service {
+                        status: Some(412),
+                        code: String::from("PreconditionFailed"),
+                        message: format!("{} changed during the copy", input.source_key),
+                        request_id: String::new(),
+                    });
+                }
+            }
         }
+        let result = try!(self.sign_and_dispatch(&mut request, true));
+        match result.status {
+            200 | 204 => Ok(output),
+            status => Err(CtError::from_response(status, &result.body)),
+        }
+    }
+}
+
+/// Record in `output` the source which was copied.
+fn copied(
+    mut output: ObjectCopyOutput,
+    input: &ObjectCopyRequest,
+    source: &CopySource,
+) -> ObjectCopyOutput {
+    output.source_e_tag = input.if_match.clone().or_else(|| source.e_tag.clone());
+    output.source_version_id = input
+        .source_version_id
+        .clone()
+        .or_else(|| source.version_id.clone());
+    output
+}
+
+fn head_source(ct: &CTClient, input: &ObjectCopyRequest) -> Result<CopySource, CtError> {
+    let path = format!("/{}", input.source_key);
+    let mut request = SignedRequest::new(
+        "HEAD",
+        "s3",
+        ct.region(),
+        &input.source_bucket,
+        &path,
+        ct.endpoint(),
+    );
+    request.set_hostname(Some(ct.hostname(Some(&input.source_bucket))));
+    if let Some(ref version_id) = input.source_version_id {
+        request.add_param("versionId", version_id);
+    }
+
+    let result = try!(ct.sign_and_dispatch(&mut request, true));
+    if result.status != 200 {
+        return Err(CtError::from_response(result.status, &result.body));
+    }
+
+    let mut source = CopySource::default();
+    for (name, value) in result.headers.iter() {
+        let name = name.to_lowercase();
+        if name == "content-length" {
+            source.size = try!(value.trim().parse().map_err(|_| {
+                CtError::Http(format!("invalid Content-Length {:?}", value))
+            }));
+        } else if name == "content-type" {
+            source.content_type = Some(value.to_string());
+        } else if name == "etag" {
+            source.e_tag = Some(value.to_string());
+        } else if name == "x-amz-version-id" && value != "null" {
+            source.version_id = Some(value.to_string());
+        } else if name.starts_with("x-amz-meta-") {
+            source
+                .metadata
+                .push((name["x-amz-meta-".len()..].to_string(), value.to_string()));
+        }
+    }
+    Ok(source)
+}
+
+/// Copy `source` by parts, the upload is aborted if a part or the completion fails.
+fn copy_parts(
+    ct: &CTClient,
+    input: &ObjectCopyRequest,
+    source: &CopySource,
+    part_size: u64,
+) -> Result<ObjectCopyOutput, CtError> {
+    if part_size == 0 {
+        return Err(CtError::Config(String::from("copy with empty parts")));
+    }
+    let path = format!("/{}", input.key);
+
+    let mut request =
+        SignedRequest::new("POST", "s3", ct.region(), &input.bucket, &path, ct.endpoint());
+    request.set_hostname(Some(ct.hostname(Some(&input.bucket))));
+    request.add_param("uploads", "");
+    // The metadata of the source is not copied with the parts.
+    match input.metadata_directive {
+        MetadataDirective::Copy => {
+            object_headers(&mut request, &source.content_type, &source.metadata)
+        }
+        MetadataDirective::Replace => {
+            object_headers(&mut request, &input.content_type, &input.metadata)
+        }
+    }
+    storage_headers(&mut request, input);
+    let result = try!(ct.sign_and_dispatch(&mut request, false));
+    if result.status != 200 {
+        return Err(CtError::from_response(result.status, &result.body));
+    }
+    let upload: InitiateMultipartUploadResult = try!(decode(&result.body, &[]));
+
+    let completed = copy_ranges(ct, input, source, part_size, &upload.upload_id)
+        .and_then(|parts| {
+            let mut xml = String::from("<CompleteMultipartUpload>");
+            for (index, e_tag) in parts.iter().enumerate() {
+                xml.push_str("<Part>");
+                push_element(&mut xml, "PartNumber", index + 1);
+                push_element(&mut xml, "ETag", e_tag);
+                xml.push_str("</Part>");
+            }
+            xml.push_str("</CompleteMultipartUpload>");
+            let payload = xml.into_bytes();
+
+            let mut request =
+                SignedRequest::new("POST", "s3", ct.region(), &input.bucket, &path, ct.endpoint());
+            request.set_hostname(Some(ct.hostname(Some(&input.bucket))));
+            request.add_param("uploadId", &upload.upload_id);
+            request.update_header("Content-Type", "application/xml");
+            request.set_payload(Some(&payload));
+            let result = try!(ct.sign_and_dispatch(&mut request, false));
+            copy_result(result.status, &result.body, "CompleteMultipartUploadResult")
+                .map(|output| copied(output, input, source))
+        });
+
+    if completed.is_err() {
+        let mut request =
+            SignedRequest::new("DELETE", "s3", ct.region(), &input.bucket, &path, ct.endpoint());
+        request.set_hostname(Some(ct.hostname(Some(&input.bucket))));
+        request.add_param("uploadId", &upload.upload_id);
+        if let Err(err) = ct.sign_and_dispatch(&mut request, true) {
+            warn!("abort upload {} of {}: {}", upload.upload_id, input.key, err);
+        }
+    }
+    completed
+}
+
+/// Copy the ranges of `source` with `UploadPartCopy`, returning the ETag of each part.
+fn copy_ranges(
+    ct: &CTClient,
+    input: &ObjectCopyRequest,
+    source: &CopySource,
+    part_size: u64,
+    upload_id: &str,
+) -> Result<Vec<String>, CtError> {
+    let path = format!("/{}", input.key);
+    let mut parts = Vec::new();
+    let mut start = 0;
+    while start < source.size || parts.is_empty() {
+        let end = cmp::min(start + part_size, source.size);
+        let part_number = (parts.len() + 1).to_string();
+
+        let mut request =
+            SignedRequest::new("PUT", "s3", ct.region(), &input.bucket, &path, ct.endpoint());
+        request.set_hostname(Some(ct.hostname(Some(&input.bucket))));
+        request.add_param("partNumber", &part_number);
+        request.add_param("uploadId", upload_id);
+        copy_source_headers(&mut request, input, source);
+        if end > start {
+            request.update_header(
+                "x-amz-copy-source-range",
+                &format!("bytes={}-{}", start, end - 1),
+            );
+        }
+
+        let result = try!(ct.sign_and_dispatch(&mut request, true));
+        let part = try!(copy_result(result.status, &result.body, "CopyPartResult"));
+        debug!("copy part {} of {}: {}", part_number, input.key, part.e_tag);
+        parts.push(part.e_tag);
+        start = end;
+    }
+    Ok(parts)
+}
+
+/// The source and conditions of a copy or of a part copy, the source is pinned to the
+/// ETag read by `head_source` unless `if_match` is given.
+fn copy_source_headers(
+    request: &mut SignedRequest,
+    input: &ObjectCopyRequest,
+    source: &CopySource,
+) {
+    request.update_header("x-amz-copy-source", &input.copy_source());
+    let if_match = input.if_match.clone().or_else(|| source.e_tag.clone());
+    let conditions = [
+        ("x-amz-copy-source-if-match", &if_match),
+        ("x-amz-copy-source-if-none-match", &input.if_none_match),
+        ("x-amz-copy-source-if-modified-since", &input.if_modified_since),
+        ("x-amz-copy-source-if-unmodified-since", &input.if_unmodified_since),
+    ];
+    for &(name, value) in conditions.iter() {
+        if let Some(ref value) = *value {
+            request.update_header(name, value);
+        }
+    }
+}
+
+fn object_headers(
+    request: &mut SignedRequest,
+    content_type: &Option<String>,
+    metadata: &[(String, String)],
+) {
+    if let Some(ref content_type) = *content_type {
+        request.update_header("Content-Type", content_type);
+    }
+    for &(ref name, ref value) in metadata {
+        request.update_header(&format!("x-amz-meta-{}", name), value);
+    }
+}
+
+fn storage_headers(request: &mut SignedRequest, input: &ObjectCopyRequest) {
+    if let Some(ref storage_class) = input.storage_class {
+        request.update_header("x-amz-storage-class", storage_class);
+    }
+    if let Some(ref acl) = input.acl {
+        request.update_header("x-amz-acl", &canned_acl_in_aws_format(acl));
+    }
+}
+
+/// A copy may fail after its `200 OK` was sent, the error is then in the body.
+fn copy_result(status: u16, body: &str, root: &str) -> Result<ObjectCopyOutput, CtError> {
+    match (status, ErrorDocument::from_body(body)) {
+        (200, None) => {
+            let element = try!(parse(body));
+            if element.name != root {
+                return Err(CtError::from(XmlError::new(
+                    &element.name,
+                    &format!("expected {}", root),
+                )));
+            }
+            Ok(try!(ObjectCopyOutput::from_xml(&element, root)))
+        }
+        (status, _) => Err(CtError::from_response(status, body)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, UTC};
+    use super::{copied, copy_result, multipart_form, CTClientObject, CTClientObjectCopy,
+                CopySource, MetadataDirective, ObjectCopyOutput, ObjectCopyRequest,
+                ObjectVersionsOutput, PostObjectOutput, PostObjectUploadRequest, PostPolicy};
+    use ct::credentials::StaticProvider;
+    use ct::decode::decode;
+    use ct::error::CtError;
+    use ct::sdk::CTClient;
+
+    #[test]
+    fn post_policy_conditions() {
+        let policy = PostPolicy::new("gitai.test")
+            .key_prefix("uploads/")
+            .content_length_range(1, 1024)
+            .content_type_prefix("image/")
+            .success_action_status(201)
+            .meta("owner", "gitai");
+
+        assert_eq!(policy.key_field(), "uploads/${filename}");
+        assert_eq!(
+            policy.to_json(&UTC.ymd(2017, 11, 24).and_hms(12, 0, 0)),
+            concat!(
+                r#"{"conditions":[{"bucket":"gitai.test"},"#,
+                r#"["starts-with","$key","uploads/"],"#,
+                r#"["content-length-range",1,1024],"#,
+                r#"["starts-with","$Content-Type","image/"],"#,
+                r#"{"success_action_status":"201"},"#,
+                r#"{"x-amz-meta-owner":"gitai"}],"#,
+                r#""expiration":"2017-11-24T12:00:00.000Z"}"#
+            )
+        );
+    }
+
+    #[test]
+    fn post_policy_exact_key() {
+        let policy = PostPolicy::new("gitai.test").key("a \"quoted\" key");
+
+        assert_eq!(policy.key_field(), "a \"quoted\" key");
+        assert!(
+            policy
+                .to_json(&UTC.ymd(2017, 11, 24).and_hms(12, 0, 0))
+                .contains(r#"["eq","$key","a \"quoted\" key"]"#)
+        );
+    }
+
+    #[test]
+    fn post_multipart_form() {
+        let form = PostObjectOutput {
+            url: "http://gitai.test.oos-bj2.ctyunapi.cn".to_string(),
+            fields: vec![
+                ("key".to_string(), "uploads/${filename}".to_string()),
+                ("Policy".to_string(), "e30=".to_string()),
+                ("Content-Type".to_string(), "text/".to_string()),
+            ],
+        };
+        let payload = multipart_form(
+            "BOUNDARY",
+            &PostObjectUploadRequest {
+                form: &form,
+                key: Some("uploads/a.txt".to_string()),
+                file_name: "a.txt".to_string(),
+                content_type: Some("text/plain".to_string()),
+            },
+            b"hello",
+        );
+
+        assert_eq!(
+            String::from_utf8(payload).unwrap(),
+            concat!(
+                "--BOUNDARY\r\nContent-Disposition: form-data; name=\"key\"\r\n\r\n",
+                "uploads/a.txt\r\n",
+                "--BOUNDARY\r\nContent-Disposition: form-data; name=\"Policy\"\r\n\r\n",
+                "e30=\r\n",
+                "--BOUNDARY\r\nContent-Disposition: form-data; name=\"Content-Type\"\r\n\r\n",
+                "text/plain\r\n",
+                "--BOUNDARY\r\nContent-Disposition: form-data; name=\"file\"; filename=\"a.txt\"\r\n",
+                "Content-Type: text/plain\r\n\r\n",
+                "hello\r\n--BOUNDARY--\r\n"
+            )
+        );
+
+        let unsigned = PostObjectOutput {
+            fields: form.fields[..2].to_vec(),
+            ..form.clone()
+        };
+        let ct = CTClient::new(StaticProvider::new("ak", "sk"), None, None);
+        let request = PostObjectUploadRequest {
+            form: &unsigned,
+            key: None,
+            file_name: "a.txt".to_string(),
+            content_type: Some("text/plain".to_string()),
+        };
+        match ct.post_object_upload(&request, &b"hello"[..]) {
+            Err(CtError::Config(_)) => {}
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn object_versions() {
+        let body = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ListVersionsResult xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+  <Name>gitai.test</Name>
+  <Prefix>docs/</Prefix>
+  <IsTruncated>true</IsTruncated>
+  <NextKeyMarker>docs/b.txt</NextKeyMarker>
+  <NextVersionIdMarker>3/L4kqtJl40Nr8X8gdRQBpUMLUo</NextVersionIdMarker>
+  <DeleteMarker>
+    <Key>docs/a.txt</Key>
+    <VersionId>QUpfdndhfd8438MNFDN93jdnJFkdmqnh893</VersionId>
+    <IsLatest>true</IsLatest>
+    <LastModified>2017-11-20T08:00:00.000Z</LastModified>
+  </DeleteMarker>
+  <Version>
+    <Key>docs/a.txt</Key>
+    <VersionId>null</VersionId>
+    <IsLatest>false</IsLatest>
+    <LastModified>2017-11-19T08:00:00.000Z</LastModified>
+    <ETag>"fba9dede5f27731c9771645a39863328"</ETag>
+    <Size>434234</Size>
+    <StorageClass>STANDARD</StorageClass>
+  </Version>
+  <Version>
+    <Key>docs/b.txt</Key>
+    <VersionId>3/L4kqtJl40Nr8X8gdRQBpUMLUo</VersionId>
+    <IsLatest>true</IsLatest>
+    <Size>12</Size>
+  </Version>
+  <CommonPrefixes><Prefix>docs/img/</Prefix></CommonPrefixes>
+</ListVersionsResult>"#;
+        let out: ObjectVersionsOutput = decode(body, &[]).unwrap();
+        assert!(out.is_truncated);
+        assert_eq!(out.next_key_marker, Some("docs/b.txt".to_string()));
+        assert_eq!(out.versions.len(), 2);
+        assert_eq!(out.versions[0].version_id, "null");
+        assert!(!out.versions[0].is_latest);
+        assert_eq!(out.versions[0].size, 434234);
+        assert_eq!(out.versions[1].storage_class, "");
+        assert_eq!(out.delete_markers.len(), 1);
+        assert!(out.delete_markers[0].is_latest);
+        assert_eq!(out.common_prefixes[0].prefix, "docs/img/");
+    }
+
+    #[test]
+    fn copy_request() {
+        let mut input = ObjectCopyRequest::new("photos", "2017/a b+c%.jpg", "backup", "a.jpg");
+        assert_eq!(input.metadata_directive, MetadataDirective::Copy);
+        assert_eq!(format!("{}", MetadataDirective::Replace), "REPLACE");
+        assert_eq!(input.copy_source(), "/photos/2017/a%20b%2Bc%25.jpg");
+
+        input.source_version_id = Some("3/L4kqtJl40Nr8X8gdRQBpUMLUo".to_string());
+        assert_eq!(
+            input.copy_source(),
+            "/photos/2017/a%20b%2Bc%25.jpg?versionId=3/L4kqtJl40Nr8X8gdRQBpUMLUo"
+        );
+    }
+
+    #[test]
+    fn copied_source() {
+        let mut input = ObjectCopyRequest::new("photos", "a.jpg", "backup", "a.jpg");
+        let source = CopySource {
+            e_tag: Some("\"9b2cf535f27731c974343645a3985328\"".to_string()),
+            version_id: Some("3/L4kqtJl40Nr8X8gdRQBpUMLUo".to_string()),
+            ..Default::default()
+        };
+        let out = copied(ObjectCopyOutput::default(), &input, &source);
+        assert_eq!(out.source_e_tag, source.e_tag);
+        assert_eq!(out.source_version_id, source.version_id);
+
+        input.if_match = Some("\"fba9dede5f27731c9771645a39863328\"".to_string());
+        input.source_version_id = Some("null".to_string());
+        let out = copied(ObjectCopyOutput::default(), &input, &source);
+        assert_eq!(out.source_e_tag, input.if_match);
+        assert_eq!(out.source_version_id, input.source_version_id);
+    }
+
+    #[test]
+    fn move_to_itself() {
+        let ct = CTClient::new(StaticProvider::new("ak", "sk"), None, None);
+        let input = ObjectCopyRequest::new("photos", "a.jpg", "photos", "a.jpg");
+        match ct.move_object(&input) {
+            Err(CtError::Config(message)) => assert!(message.contains("a.jpg")),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn copy_results() {
+        let body = r#"<CopyObjectResult>
+  <LastModified>2017-11-22T08:00:00.000Z</LastModified>
+  <ETag>"9b2cf535f27731c974343645a3985328"</ETag>
+</CopyObjectResult>"#;
+        let out = copy_result(200, body, "CopyObjectResult").unwrap();
+        assert_eq!(out.e_tag, "\"9b2cf535f27731c974343645a3985328\"");
+        assert_eq!(out.last_modified, "2017-11-22T08:00:00.000Z");
+        assert!(copy_result(200, body, "CopyPartResult").is_err());
+
+        let failed = "<Error><Code>InternalError</Code><Message>copy failed</Message></Error>";
+        let err = copy_result(200, failed, "CopyObjectResult").unwrap_err();
+        assert_eq!(err.code(), Some("InternalError"));
+
+        let failed = "<Error><Code>PreconditionFailed</Code></Error>";
+        let err = copy_result(412, failed, "CopyObjectResult").unwrap_err();
+        assert_eq!(err.code(), Some("PreconditionFailed"));
     }
 }
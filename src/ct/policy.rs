@@ -0,0 +1,651 @@
+// Copyright 2017 Gitai<i@gitai.me> All rights reserved.
+//
+// Permission is hereby granted, free of charge, to any person
+// obtaining a copy of this software and associated documentation
+// files (the "Software"), to deal in the Software without restriction,
+// including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall
+// be included in all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND,
+// EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES
+// OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.
+// IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR
+// ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF
+// CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION
+// WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+//! Bucket policy documents
+//!
+//! A `BucketPolicy` is read from and written to the JSON policy language, and
+//! checked locally by `validate` before it is sent:
+//!
+//! ```
+//! let policy = BucketPolicy::new(vec![
+//!     PolicyStatement::new(PolicyEffect::Allow, "s3:GetObject", "arn:aws:s3:::photos/*")
+//!         .condition(PolicyCondition::source_ip(&["192.168.0.0/16"]))
+//!         .condition(PolicyCondition::referer(&["http://*.example.com/*"])),
+//! ]);
+//! try!(policy.validate("photos"));
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt::{Display, Error, Formatter};
+use std::net::IpAddr;
+
+use rustc_serialize::json::Json;
+
+use ct::error::CtError;
+
+/// Versions of the policy language
+pub const POLICY_VERSIONS: [&'static str; 2] = ["2012-10-17", "2008-10-17"];
+
+/// Condition operators understood by OOS
+pub const CONDITION_OPERATORS: [&'static str; 7] = [
+    "Null",
+    "IpAddress",
+    "NotIpAddress",
+    "StringLike",
+    "StringNotLike",
+    "StringEquals",
+    "StringNotEquals",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PolicyEffect {
+    Allow,
+    Deny,
+}
+
+impl Display for PolicyEffect {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match self {
+            &PolicyEffect::Allow => write!(f, "Allow"),
+            &PolicyEffect::Deny => write!(f, "Deny"),
+        }
+    }
+}
+
+/// Who the statement applies to
+#[derive(Debug, Clone, PartialEq)]
+pub enum Principal {
+    /// `"*"`, everyone including anonymous users
+    Anyone,
+    /// `{"AWS": [...]}`, accounts or users by ARN
+    Accounts(Vec<String>),
+}
+
+impl Display for Principal {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match self {
+            &Principal::Anyone => write!(f, "*"),
+            &Principal::Accounts(ref arns) => write!(f, "{}", arns.join(", ")),
+        }
+    }
+}
+
+/// `{"<operator>": {"<key>": [<values>]}}`
+#[derive(Debug, Clone, PartialEq)]
+pub struct PolicyCondition {
+    pub operator: String,
+    pub key: String,
+    pub values: Vec<String>,
+}
+
+impl PolicyCondition {
+    pub fn new(operator: &str, key: &str, values: &[&str]) -> PolicyCondition {
+        PolicyCondition {
+            operator: operator.to_string(),
+            key: key.to_string(),
+            values: values.iter().map(|value| value.to_string()).collect(),
+        }
+    }
+
+    /// Requests from the addresses or CIDR blocks, e.g. `10.0.0.0/8`
+    pub fn source_ip(cidrs: &[&str]) -> PolicyCondition {
+        PolicyCondition::new("IpAddress", "aws:SourceIp", cidrs)
+    }
+
+    /// Requests whose `Referer` header matches one of the patterns, `*` is a wildcard
+    pub fn referer(patterns: &[&str]) -> PolicyCondition {
+        PolicyCondition::new("StringLike", "aws:Referer", patterns)
+    }
+}
+
+impl Display for PolicyCondition {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "{} {} {}", self.key, self.operator, self.values.join(", "))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PolicyStatement {
+    pub sid: Option<String>,
+    pub effect: PolicyEffect,
+    pub principal: Principal,
+    /// e.g. `s3:GetObject` or `s3:*`
+    pub actions: Vec<String>,
+    /// e.g. `arn:aws:s3:::bucket/*`
+    pub resources: Vec<String>,
+    pub conditions: Vec<PolicyCondition>,
+}
+
+impl PolicyStatement {
+    /// A statement for anyone, with an action and a resource.
+    pub fn new(effect: PolicyEffect, action: &str, resource: &str) -> PolicyStatement {
+        PolicyStatement {
+            sid: None,
+            effect: effect,
+            principal: Principal::Anyone,
+            actions: vec![action.to_string()],
+            resources: vec![resource.to_string()],
+            conditions: Vec::new(),
+        }
+    }
+
+    pub fn sid(mut self, sid: &str) -> PolicyStatement {
+        self.sid = Some(sid.to_string());
+        self
+    }
+
+    pub fn condition(mut self, condition: PolicyCondition) -> PolicyStatement {
+        self.conditions.push(condition);
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BucketPolicy {
+    pub version: String,
+    pub id: Option<String>,
+    pub statements: Vec<PolicyStatement>,
+}
+
+fn invalid(message: String) -> CtError {
+    CtError::Config(format!("invalid policy: {}", message))
+}
+
+/// A string or an array of strings
+fn strings(json: &Json, name: &str) -> Result<Vec<String>, CtError> {
+    match *json {
+        Json::String(ref value) => Ok(vec![value.clone()]),
+        Json::Array(ref values) => values
+            .iter()
+            .map(|value| match value.as_string() {
+                Some(value) => Ok(value.to_string()),
+                None => Err(invalid(format!("{} must be strings", name))),
+            })
+            .collect(),
+        _ => Err(invalid(format!("{} must be a string or an array", name))),
+    }
+}
+
+fn string_array(values: &[String]) -> Json {
+    match values.len() {
+        1 => Json::String(values[0].clone()),
+        _ => Json::Array(values.iter().map(|value| Json::String(value.clone())).collect()),
+    }
+}
+
+fn parse_principal(json: &Json) -> Result<Principal, CtError> {
+    if json.as_string() == Some("*") {
+        return Ok(Principal::Anyone);
+    }
+    match json.find("AWS") {
+        Some(aws) => {
+            let arns = try!(strings(aws, "Principal.AWS"));
+            match arns.len() == 1 && arns[0] == "*" {
+                true => Ok(Principal::Anyone),
+                false => Ok(Principal::Accounts(arns)),
+            }
+        }
+        None => Err(invalid(String::from("Principal must be \"*\" or {\"AWS\": ...}"))),
+    }
+}
+
+fn parse_conditions(json: &Json) -> Result<Vec<PolicyCondition>, CtError> {
+    let operators = match json.as_object() {
+        Some(operators) => operators,
+        None => return Err(invalid(String::from("Condition must be an object"))),
+    };
+    let mut conditions = Vec::new();
+    for (operator, keys) in operators {
+        let keys = match keys.as_object() {
+            Some(keys) => keys,
+            None => return Err(invalid(format!("Condition.{} must be an object", operator))),
+        };
+        for (key, values) in keys {
+            conditions.push(PolicyCondition {
+                operator: operator.clone(),
+                key: key.clone(),
+                values: try!(strings(values, &format!("Condition.{}.{}", operator, key))),
+            });
+        }
+    }
+    Ok(conditions)
+}
+
+fn parse_statement(json: &Json) -> Result<PolicyStatement, CtError> {
+    let field = |name: &str| match json.find(name) {
+        Some(value) => Ok(value),
+        None => Err(invalid(format!("Statement without {}", name))),
+    };
+    Ok(PolicyStatement {
+        sid: json.find("Sid").and_then(|sid| sid.as_string()).map(|sid| sid.to_string()),
+        effect: match try!(field("Effect")).as_string() {
+            Some("Allow") => PolicyEffect::Allow,
+            Some("Deny") => PolicyEffect::Deny,
+            _ => return Err(invalid(String::from("Effect must be Allow or Deny"))),
+        },
+        principal: try!(parse_principal(try!(field("Principal")))),
+        actions: try!(strings(try!(field("Action")), "Action")),
+        resources: try!(strings(try!(field("Resource")), "Resource")),
+        conditions: match json.find("Condition") {
+            Some(condition) => try!(parse_conditions(condition)),
+            None => Vec::new(),
+        },
+    })
+}
+
+/// Check a CIDR block or an address.
+fn valid_cidr(cidr: &str) -> bool {
+    let (address, prefix) = match cidr.find('/') {
+        Some(index) => (&cidr[..index], Some(&cidr[index + 1..])),
+        None => (cidr, None),
+    };
+    let max = match address.parse::<IpAddr>() {
+        Ok(IpAddr::V4(_)) => 32,
+        Ok(IpAddr::V6(_)) => 128,
+        Err(_) => return false,
+    };
+    match prefix {
+        Some(prefix) => match prefix.parse::<u8>() {
+            Ok(prefix) => prefix <= max,
+            Err(_) => false,
+        },
+        None => true,
+    }
+}
+
+impl BucketPolicy {
+    /// A policy of the current version.
+    pub fn new(statements: Vec<PolicyStatement>) -> BucketPolicy {
+        BucketPolicy {
+            version: POLICY_VERSIONS[0].to_string(),
+            id: None,
+            statements: statements,
+        }
+    }
+
+    pub fn from_json(document: &str) -> Result<BucketPolicy, CtError> {
+        let json = try!(Json::from_str(document).map_err(|err| invalid(format!("{}", err))));
+        let statements = match json.find("Statement") {
+            Some(&Json::Array(ref statements)) => statements.iter().collect::<Vec<_>>(),
+            Some(statement @ &Json::Object(_)) => vec![statement],
+            _ => return Err(invalid(String::from("Statement must be an array"))),
+        };
+        Ok(BucketPolicy {
+            version: json.find("Version")
+                .and_then(|version| version.as_string())
+                .unwrap_or("")
+                .to_string(),
+            id: json.find("Id").and_then(|id| id.as_string()).map(|id| id.to_string()),
+            statements: try!(
+                statements
+                    .into_iter()
+                    .map(parse_statement)
+                    .collect::<Result<Vec<_>, CtError>>()
+            ),
+        })
+    }
+
+    pub fn to_json(&self) -> Json {
+        let mut policy = BTreeMap::new();
+        policy.insert(String::from("Version"), Json::String(self.version.clone()));
+        if let Some(ref id) = self.id {
+            policy.insert(String::from("Id"), Json::String(id.clone()));
+        }
+        let statements = self.statements
+            .iter()
+            .map(|statement| {
+                let mut object = BTreeMap::new();
+                if let Some(ref sid) = statement.sid {
+                    object.insert(String::from("Sid"), Json::String(sid.clone()));
+                }
+                object.insert(
+                    String::from("Effect"),
+                    Json::String(statement.effect.to_string()),
+                );
+                object.insert(
+                    String::from("Principal"),
+                    match statement.principal {
+                        Principal::Anyone => Json::String(String::from("*")),
+                        Principal::Accounts(ref arns) => {
+                            let mut aws = BTreeMap::new();
+                            aws.insert(String::from("AWS"), string_array(arns));
+                            Json::Object(aws)
+                        }
+                    },
+                );
+                object.insert(String::from("Action"), string_array(&statement.actions));
+                object.insert(String::from("Resource"), string_array(&statement.resources));
+                if !statement.conditions.is_empty() {
+                    let mut operators = BTreeMap::new();
+                    for condition in &statement.conditions {
+                        let keys = operators
+                            .entry(condition.operator.clone())
+                            .or_insert_with(BTreeMap::new);
+                        keys.insert(condition.key.clone(), string_array(&condition.values));
+                    }
+                    object.insert(
+                        String::from("Condition"),
+                        Json::Object(
+                            operators
+                                .into_iter()
+                                .map(|(operator, keys)| (operator, Json::Object(keys)))
+                                .collect(),
+                        ),
+                    );
+                }
+                Json::Object(object)
+            })
+            .collect();
+        policy.insert(String::from("Statement"), Json::Array(statements));
+        Json::Object(policy)
+    }
+
+    /// Check the policy of `bucket`: version, unique `Sid`, `s3:` actions, resources of
+    /// the bucket and the values of the IP conditions.
+    pub fn validate(&self, bucket: &str) -> Result<(), CtError> {
+        if !POLICY_VERSIONS.iter().any(|version| *version == self.version) {
+            return Err(invalid(format!(
+                "Version must be one of {}",
+                POLICY_VERSIONS.join(", ")
+            )));
+        }
+        if self.statements.is_empty() {
+            return Err(invalid(String::from("no statement")));
+        }
+        let arn = format!("arn:aws:s3:::{}", bucket);
+        for (index, statement) in self.statements.iter().enumerate() {
+            let name = match statement.sid {
+                Some(ref sid) => format!("statement {:?}", sid),
+                None => format!("statement {}", index),
+            };
+            if let Some(ref sid) = statement.sid {
+                if self.statements[..index].iter().any(|s| s.sid.as_ref() == Some(sid)) {
+                    return Err(invalid(format!("{}: duplicate Sid", name)));
+                }
+            }
+            if let Principal::Accounts(ref arns) = statement.principal {
+                if arns.is_empty() {
+                    return Err(invalid(format!("{}: empty Principal", name)));
+                }
+            }
+            if statement.actions.is_empty() {
+                return Err(invalid(format!("{}: no Action", name)));
+            }
+            for action in &statement.actions {
+                if action != "*" && !(action.starts_with("s3:") && action.len() > 3) {
+                    return Err(invalid(format!("{}: unknown action {:?}", name, action)));
+                }
+            }
+            if statement.resources.is_empty() {
+                return Err(invalid(format!("{}: no Resource", name)));
+            }
+            for resource in &statement.resources {
+                if *resource != arn && !resource.starts_with(&format!("{}/", arn)) {
+                    return Err(invalid(format!(
+                        "{}: resource {:?} is not in bucket {}",
+                        name,
+                        resource,
+                        bucket
+                    )));
+                }
+            }
+            for condition in &statement.conditions {
+                if !CONDITION_OPERATORS.iter().any(|op| *op == condition.operator) {
+                    return Err(invalid(format!(
+                        "{}: unknown condition operator {:?}",
+                        name,
+                        condition.operator
+                    )));
+                }
+                if condition.values.is_empty() {
+                    return Err(invalid(format!("{}: condition without value", name)));
+                }
+                if condition.key == "aws:SourceIp" {
+                    if let Some(cidr) = condition.values.iter().find(|cidr| !valid_cidr(cidr)) {
+                        return Err(invalid(format!("{}: invalid address {:?}", name, cidr)));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for BucketPolicy {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "{}", self.to_json().pretty())
+    }
+}
+
+/// `Sid` of the statement denying the referers out of the allow list
+pub const REFERER_ALLOW_SID: &'static str = "ct-referer-allow";
+
+/// `Sid` of the statement denying the referers of the deny list
+pub const REFERER_DENY_SID: &'static str = "ct-referer-deny";
+
+/// Hotlink protection of the objects of a bucket, by the `Referer` header of `GetObject`
+///
+/// It is kept in the bucket policy as statements with the `Sid`s `REFERER_ALLOW_SID` and
+/// `REFERER_DENY_SID`, the other statements are left untouched. Patterns may use `*`,
+/// e.g. `http://*.example.com/*`.
+#[derive(Debug, Clone, PartialEq, RustcDecodable, RustcEncodable)]
+pub struct RefererConfiguration {
+    /// Only these referers may get objects, any referer when empty
+    pub allowed: Vec<String>,
+    /// These referers may not get objects
+    pub denied: Vec<String>,
+    /// Requests without `Referer`, e.g. from a browser address bar, bypass the allow list
+    /// (the default). Otherwise they are denied, even when signed by the owner.
+    ///
+    /// Only applies to the allow list, see `validate`.
+    pub allow_empty: bool,
+}
+
+impl Default for RefererConfiguration {
+    fn default() -> RefererConfiguration {
+        RefererConfiguration {
+            allowed: Vec::new(),
+            denied: Vec::new(),
+            allow_empty: true,
+        }
+    }
+}
+
+fn has_sid(statement: &PolicyStatement, sid: &str) -> bool {
+    statement.sid.as_ref().map(|s| s.as_str()) == Some(sid)
+}
+
+impl RefererConfiguration {
+    /// Read the managed statements of `policy`.
+    pub fn from_policy(policy: &BucketPolicy) -> RefererConfiguration {
+        let mut referer = RefererConfiguration::default();
+        for statement in &policy.statements {
+            for condition in &statement.conditions {
+                match (condition.operator.as_str(), condition.key.as_str()) {
+                    ("StringNotLike", "aws:Referer") if has_sid(statement, REFERER_ALLOW_SID) => {
+                        referer.allowed = condition.values.clone()
+                    }
+                    ("StringLike", "aws:Referer") if has_sid(statement, REFERER_DENY_SID) => {
+                        referer.denied = condition.values.clone()
+                    }
+                    _ => {}
+                }
+            }
+            if has_sid(statement, REFERER_ALLOW_SID) {
+                referer.allow_empty = statement.conditions.iter().any(|condition| {
+                    condition.operator == "Null" && condition.key == "aws:Referer"
+                });
+            }
+        }
+        referer
+    }
+
+    /// Empty referers can only be denied along with an allow list.
+    pub fn validate(&self) -> Result<(), CtError> {
+        if self.allowed.is_empty() && !self.allow_empty {
+            return Err(CtError::Config(String::from(
+                "invalid referer configuration: denying empty referers requires allowed referers",
+            )));
+        }
+        Ok(())
+    }
+
+    /// The managed statements for `bucket`
+    pub fn statements(&self, bucket: &str) -> Vec<PolicyStatement> {
+        let objects = format!("arn:aws:s3:::{}/*", bucket);
+        let mut statements = Vec::new();
+        if !self.allowed.is_empty() {
+            let allowed = self.allowed.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+            let mut statement = PolicyStatement::new(PolicyEffect::Deny, "s3:GetObject", &objects)
+                .sid(REFERER_ALLOW_SID)
+                .condition(PolicyCondition::new("StringNotLike", "aws:Referer", &allowed));
+            if self.allow_empty {
+                let present = PolicyCondition::new("Null", "aws:Referer", &["false"]);
+                statement = statement.condition(present);
+            }
+            statements.push(statement);
+        }
+        if !self.denied.is_empty() {
+            let denied = self.denied.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+            statements.push(
+                PolicyStatement::new(PolicyEffect::Deny, "s3:GetObject", &objects)
+                    .sid(REFERER_DENY_SID)
+                    .condition(PolicyCondition::new("StringLike", "aws:Referer", &denied)),
+            );
+        }
+        statements
+    }
+}
+
+impl BucketPolicy {
+    /// Replace the managed referer statements of the policy of `bucket`.
+    pub fn set_referer(&mut self, bucket: &str, referer: &RefererConfiguration) {
+        self.statements.retain(|statement| {
+            !has_sid(statement, REFERER_ALLOW_SID) && !has_sid(statement, REFERER_DENY_SID)
+        });
+        self.statements.extend(referer.statements(bucket));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static DOCUMENT: &'static str = r#"{
+  "Version": "2012-10-17",
+  "Statement": [
+    {
+      "Sid": "read",
+      "Effect": "Allow",
+      "Principal": {"AWS": "*"},
+      "Action": ["s3:GetObject"],
+      "Resource": "arn:aws:s3:::photos/*",
+      "Condition": {
+        "IpAddress": {"aws:SourceIp": ["192.168.0.0/16", "::1"]},
+        "StringLike": {"aws:Referer": "http://*.example.com/*"}
+      }
+    }
+  ]
+}"#;
+
+    #[test]
+    fn parse_policy() {
+        let policy = BucketPolicy::from_json(DOCUMENT).unwrap();
+        let statement = &policy.statements[0];
+        assert_eq!(statement.principal, Principal::Anyone);
+        assert_eq!(statement.actions, vec![String::from("s3:GetObject")]);
+        assert_eq!(statement.conditions.len(), 2);
+        assert!(policy.validate("photos").is_ok());
+        assert!(policy.validate("videos").is_err());
+
+        let again = BucketPolicy::from_json(&policy.to_string()).unwrap();
+        assert_eq!(again, policy);
+
+        let built = BucketPolicy::new(vec![
+            PolicyStatement::new(PolicyEffect::Allow, "s3:GetObject", "arn:aws:s3:::photos/*")
+                .sid("read")
+                .condition(PolicyCondition::source_ip(&["192.168.0.0/16", "::1"]))
+                .condition(PolicyCondition::referer(&["http://*.example.com/*"])),
+        ]);
+        assert_eq!(built, policy);
+    }
+
+    #[test]
+    fn validate_policy() {
+        let policy = BucketPolicy::from_json(DOCUMENT).unwrap();
+
+        let mut invalid = policy.clone();
+        invalid.version = String::from("2017-01-01");
+        assert!(invalid.validate("photos").is_err());
+
+        let mut invalid = policy.clone();
+        invalid.statements[0].actions = vec![String::from("GetObject")];
+        assert!(invalid.validate("photos").is_err());
+
+        let mut invalid = policy.clone();
+        invalid.statements[0].conditions[0].values = vec![String::from("192.168.0.0/33")];
+        assert!(invalid.validate("photos").is_err());
+
+        let mut invalid = policy.clone();
+        invalid.statements.push(policy.statements[0].clone());
+        assert!(invalid.validate("photos").is_err());
+
+        assert!(BucketPolicy::from_json(r#"{"Statement": [{"Effect": "Maybe"}]}"#).is_err());
+    }
+
+    #[test]
+    fn referer_statements() {
+        let noempty = RefererConfiguration {
+            allow_empty: false,
+            ..RefererConfiguration::default()
+        };
+        assert!(noempty.validate().is_err());
+        assert!(RefererConfiguration::default().validate().is_ok());
+
+        let mut policy = BucketPolicy::from_json(DOCUMENT).unwrap();
+        let referer = RefererConfiguration {
+            allowed: vec![String::from("http://*.example.com/*")],
+            denied: vec![String::from("http://evil.com/*")],
+            allow_empty: true,
+        };
+        policy.set_referer("photos", &referer);
+        assert_eq!(policy.statements.len(), 3);
+        assert!(policy.validate("photos").is_ok());
+        assert_eq!(RefererConfiguration::from_policy(&policy), referer);
+
+        let json = BucketPolicy::from_json(&policy.to_string()).unwrap();
+        assert_eq!(RefererConfiguration::from_policy(&json), referer);
+
+        let noempty = RefererConfiguration {
+            allow_empty: false,
+            ..referer.clone()
+        };
+        policy.set_referer("photos", &noempty);
+        assert_eq!(RefererConfiguration::from_policy(&policy), noempty);
+
+        policy.set_referer("photos", &RefererConfiguration::default());
+        assert_eq!(policy.statements.len(), 1);
+        assert_eq!(
+            RefererConfiguration::from_policy(&policy),
+            RefererConfiguration::default()
+        );
+    }
+}
@@ -25,7 +25,6 @@ use url::Url;
 use chrono::UTC;
 use md5::{Digest, Md5};
 
-use hyper::client::Client;
 use hyper::header::Headers;
 
 use std::str;
@@ -43,8 +42,9 @@ use url::percent_encoding::{utf8_percent_encode, DEFAULT_ENCODE_SET, QUERY_ENCOD
 
 use aws_sdk_rust::aws::common::region::Region;
 use aws_sdk_rust::aws::common::params::Params;
+use aws_sdk_rust::aws::common::request::HttpResponse;
 use aws_sdk_rust::aws::common::signature::SignedRequest;
-use aws_sdk_rust::aws::common::credentials::{AwsCredentials, DefaultCredentialsProvider};
+use aws_sdk_rust::aws::common::credentials::AwsCredentials;
 use aws_sdk_rust::aws::s3::endpoint::{Endpoint, Signature};
 use aws_sdk_rust::aws::s3::s3client::S3Client;
 
@@ -214,13 +214,50 @@ impl<'a> CTSignedRequest<'a> for SignedRequest<'a> {
 /// encryption is done using a one-time randomly generated content encryption
 /// key (CEK) per S3 object.
 use std::ops::Deref;
+use std::sync::{Arc, Mutex};
 use bytes::{BufMut, Bytes, BytesMut};
-use aws_sdk_rust::aws::common::credentials::AwsCredentialsProvider;
+use aws_sdk_rust::aws::common::credentials::{AwsCredentialsProvider, CredentialsError};
+
+/// A credentials provider which can be shared by clients of several threads.
+///
+/// The providers of aws-sdk-rust cache the credentials in a `RefCell`, so the
+/// wrapped provider is locked while it is asked for credentials. Clones share
+/// the wrapped provider and its cache.
+#[derive(Clone)]
+pub struct SharedCredentialsProvider {
+    inner: Arc<Mutex<Box<AwsCredentialsProvider + Send>>>,
+}
 
-/// A trait to set the CTYun OOS Config default, like SignV2 and Endpoint.
+impl SharedCredentialsProvider {
+    pub fn new<P>(provider: P) -> SharedCredentialsProvider
+    where
+        P: AwsCredentialsProvider + Send + 'static,
+    {
+        SharedCredentialsProvider {
+            inner: Arc::new(Mutex::new(Box::new(provider))),
+        }
+    }
+}
+
+impl AwsCredentialsProvider for SharedCredentialsProvider {
+    fn credentials(&self) -> Result<AwsCredentials, CredentialsError> {
+        match self.inner.lock() {
+            Ok(provider) => provider.credentials(),
+            Err(_) => Err(CredentialsError::new("credentials provider lock poisoned")),
+        }
+    }
+}
 
+/// The S3 client wrapped by `CTClient`.
+pub type CTS3Client = S3Client<SharedCredentialsProvider, CtDispatcher>;
+
+/// A trait to set the CTYun OOS Config default, like SignV2 and Endpoint.
+///
+/// `CTClient` is `Send + Sync` and cheap to clone: clones share the credentials
+/// provider and the connection pool, so one client can be built once and handed
+/// to worker threads.
 pub struct CTClient {
-    p: S3Client<DefaultCredentialsProvider, Client>,
+    p: CTS3Client,
     /// Encryption password (key)
     password: String,
     /// Encryption type (method)
@@ -230,20 +267,57 @@ pub struct CTClient {
 }
 
 impl Deref for CTClient {
-    type Target = S3Client<DefaultCredentialsProvider, Client>;
-    fn deref<'a>(&'a self) -> &'a S3Client<DefaultCredentialsProvider, Client> {
+    type Target = CTS3Client;
+    fn deref<'a>(&'a self) -> &'a CTS3Client {
         &self.p
     }
 }
 
+impl Clone for CTClient {
+    fn clone(&self) -> CTClient {
+        CTClient {
+            password: self.password.clone(),
+            method: self.method,
+            enc_key: self.enc_key.clone(),
+            p: S3Client::with_request_dispatcher(
+                self.p.dispatcher.clone(),
+                self.p.credentials_provider.clone(),
+                self.p.endpoint().clone(),
+            ),
+        }
+    }
+}
+
 use ct::crypto_io::CipherType;
+use ct::credentials::CredentialsChain;
+use ct::dispatcher::{CtDispatcher, HttpConfig, RetryPolicy};
+use ct::error::CtError;
 
 impl CTClient {
-    pub fn new(
-        credentials_provider: DefaultCredentialsProvider,
+    /// Accept any `AwsCredentialsProvider`, see `ct::credentials` for the built-in ones.
+    pub fn new<P>(
+        credentials_provider: P,
+        pwd: Option<String>,
+        method: Option<CipherType>,
+    ) -> CTClient
+    where
+        P: AwsCredentialsProvider + Send + 'static,
+    {
+        // Direct connections, see `with_config` for a proxy or a custom CA
+        let dispatcher = CtDispatcher::new(RetryPolicy::default()).expect("TLS initialization");
+        CTClient::with_dispatcher(credentials_provider, dispatcher, pwd, method)
+    }
+
+    /// Use `dispatcher` and its connection pool, e.g. one built from a custom `HttpConfig`.
+    pub fn with_dispatcher<P>(
+        credentials_provider: P,
+        dispatcher: CtDispatcher,
         pwd: Option<String>,
         method: Option<CipherType>,
-    ) -> CTClient {
+    ) -> CTClient
+    where
+        P: AwsCredentialsProvider + Send + 'static,
+    {
         // Init new s3 connect
         // V4 is the default signature for AWS. However, other systems also use V2.
         let endpoint = Endpoint::new(
@@ -275,10 +349,59 @@ impl CTClient {
             password: pwd,
             method: method,
             enc_key: enc_key,
-            p: S3Client::new(credentials_provider, endpoint),
+            p: S3Client::with_request_dispatcher(
+                dispatcher,
+                SharedCredentialsProvider::new(credentials_provider),
+                endpoint,
+            ),
         }
     }
 
+    /// Replace the default `RetryPolicy` (3 attempts of idempotent requests).
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.p.dispatcher.set_retry_policy(retry);
+        self
+    }
+
+    /// Connect with `config`, keeping the retry policy.
+    ///
+    /// ```
+    /// let s3 = CTClient::default_client().with_config(HttpConfig::from_env()?)?;
+    /// ```
+    pub fn with_config(mut self, config: HttpConfig) -> Result<Self, CtError> {
+        let retry = self.p.dispatcher.retry_policy().clone();
+        let dispatcher = try!(CtDispatcher::with_config(&config, retry));
+        self.p = S3Client::with_request_dispatcher(
+            dispatcher,
+            self.p.credentials_provider.clone(),
+            self.p.endpoint().clone(),
+        );
+        Ok(self)
+    }
+
+    /// Encrypt the objects of the `*_securely` methods with `pwd` and `method`.
+    pub fn with_encryption(mut self, pwd: String, method: CipherType) -> Self {
+        self.enc_key = method.bytes_to_key(pwd.as_bytes());
+        self.method = method;
+        self.password = pwd;
+        self
+    }
+
+    /// Sign a hand-built `request` and send it with the retry policy.
+    ///
+    /// `idempotent` overrides the guess from the method, e.g. read only IAM actions use `POST`.
+    pub fn sign_and_dispatch(
+        &self,
+        request: &mut SignedRequest,
+        idempotent: bool,
+    ) -> Result<HttpResponse, CtError> {
+        let credentials = try!(self.credentials_provider().credentials());
+        request.sign(&credentials);
+        self.dispatcher
+            .dispatch_with(request, idempotent)
+            .map_err(|err| CtError::Http(format!("{}", err)))
+    }
+
     /// Get encryption key
     pub fn key(&self) -> &[u8] {
         &self.enc_key[..]
@@ -295,16 +418,17 @@ impl CTClient {
     }
 
     /// Set the CTYun OOS Config default
+    ///
+    /// Credentials come from `CredentialsChain::default()`, cached; when none is found the
+    /// requests fail with a `CtError::Credentials` listing the sources tried.
     pub fn default_client() -> Self {
-        let credentials_provider = DefaultCredentialsProvider::new(None).unwrap();
-        CTClient::new(credentials_provider, None, None)
+        CTClient::new(CredentialsChain::default().cached(), None, None)
     }
 
     /// Set the CTYun OOS Config default
     #[allow(unused_variables)]
     pub fn default_securely_client(pwd: String, encrypt_method: CipherType) -> Self {
-        let credentials_provider = DefaultCredentialsProvider::new(None).unwrap();
-        CTClient::new(credentials_provider,
+        CTClient::new(CredentialsChain::default().cached(),
                       Some(pwd),
                       Some(encrypt_method))
     }
@@ -358,6 +482,14 @@ pub fn md5(input: &[u8]) -> String {
     sh.hash().to_base64(STANDARD)
 }
 
+/// HMAC-SHA1 of `input` keyed by the secret access key, base64 encoded (SignV2).
+pub fn hmac_sha1(key: &str, input: &[u8]) -> String {
+    let hmac_pkey = PKey::hmac(key.as_bytes()).unwrap();
+    let mut hmac = Signer::new(MessageDigest::sha1(), &hmac_pkey).unwrap();
+    let _ = hmac.write_all(input);
+    hmac.finish().unwrap().to_base64(STANDARD)
+}
+
 #[inline]
 fn encode_uri(uri: &str) -> String {
     utf8_percent_encode(uri, QUERY_ENCODE_SET).collect::<String>()
@@ -438,3 +570,16 @@ fn canonical_resources_v2(bucket: &str, path: &str, is_bucket_virtual: bool) ->
     }
 }
 // V2 Signature related - End
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_send_sync<T: Send + Sync + Clone>() {}
+
+    #[test]
+    fn client_is_shareable() {
+        assert_send_sync::<CTClient>();
+        assert_send_sync::<SharedCredentialsProvider>();
+    }
+}
@@ -0,0 +1,441 @@
+// Copyright 2017 Gitai<i@gitai.me> All rights reserved.
+//
+// Permission is hereby granted, free of charge, to any person
+// obtaining a copy of this software and associated documentation
+// files (the "Software"), to deal in the Software without restriction,
+// including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall
+// be included in all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND,
+// EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES
+// OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.
+// IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR
+// ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF
+// CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION
+// WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+//! Usage statistics of the buckets
+//!
+//! Capacity, traffic and request counts are reported by day or by hour for a
+//! date range, per bucket or for the whole account.
+//!
+//! The host and the `GetCapacity`, `GetTraffics` and `GetRequests` actions follow
+//! the statistics API of the CTYun OOS developer documentation. They have not been
+//! checked against the live service, `StatsRequest::host` overrides the host.
+
+use std::collections::BTreeMap;
+use std::fmt::{Display, Error, Formatter};
+
+use chrono::NaiveDate;
+
+use ct::sdk::CTClient;
+use ct::error::CtError;
+use ct::iam::IamRequest;
+
+/// Default host of the statistics API
+pub const STATS_HOST: &'static str = "oos-cn-mg.ctyunapi.cn";
+
+/// Granularity of the statistics
+#[derive(Debug, Clone, Copy, PartialEq, RustcDecodable, RustcEncodable)]
+pub enum StatsFreq {
+    ByHour,
+    ByDay,
+}
+
+impl Display for StatsFreq {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match self {
+            &StatsFreq::ByHour => write!(f, "byHour"),
+            &StatsFreq::ByDay => write!(f, "byDay"),
+        }
+    }
+}
+
+impl Default for StatsFreq {
+    fn default() -> Self {
+        StatsFreq::ByDay
+    }
+}
+
+/// Date range of the statistics, both days included
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatsRequest {
+    pub begin_date: NaiveDate,
+    pub end_date: NaiveDate,
+    /// All the buckets when `None`
+    pub bucket: Option<String>,
+    pub freq: StatsFreq,
+    /// `STATS_HOST` when `None`
+    pub host: Option<String>,
+}
+
+impl StatsRequest {
+    pub fn new(begin_date: NaiveDate, end_date: NaiveDate) -> StatsRequest {
+        StatsRequest {
+            begin_date: begin_date,
+            end_date: end_date,
+            bucket: None,
+            freq: StatsFreq::default(),
+            host: None,
+        }
+    }
+
+    fn host(&self) -> &str {
+        self.host.as_ref().map(|host| host.as_str()).unwrap_or(STATS_HOST)
+    }
+
+    fn form(&self, action: &str) -> Result<IamRequest, CtError> {
+        if self.begin_date > self.end_date {
+            return Err(CtError::Config(format!(
+                "begin date {} is after end date {}",
+                self.begin_date,
+                self.end_date
+            )));
+        }
+        Ok(IamRequest::new(action)
+            .param("BeginDate", self.begin_date.format("%Y%m%d"))
+            .param("EndDate", self.end_date.format("%Y%m%d"))
+            .param_opt("Bucket", self.bucket.as_ref())
+            .param("Freq", self.freq))
+    }
+}
+
+/// Stored bytes at `date`
+#[derive(Debug, Default, Clone, RustcDecodable, RustcEncodable)]
+pub struct CapacityData {
+    pub date: String,
+    pub bucket: String,
+    pub region: String,
+    pub capacity: u64,
+}
+
+xml_struct! {
+    CapacityData {
+        "Date" => date,
+        "Bucket" => bucket,
+        "Region" => region,
+        "Capacity" => capacity,
+    }
+}
+
+/// Bytes transferred during `date`
+#[derive(Debug, Default, Clone, RustcDecodable, RustcEncodable)]
+pub struct TrafficData {
+    pub date: String,
+    pub bucket: String,
+    pub region: String,
+    pub upload: u64,
+    pub download: u64,
+}
+
+xml_struct! {
+    TrafficData {
+        "Date" => date,
+        "Bucket" => bucket,
+        "Region" => region,
+        "Upload" => upload,
+        "Download" => download,
+    }
+}
+
+/// Requests received during `date`
+#[derive(Debug, Default, Clone, RustcDecodable, RustcEncodable)]
+pub struct RequestsData {
+    pub date: String,
+    pub bucket: String,
+    pub region: String,
+    pub get: u64,
+    pub head: u64,
+    pub put: u64,
+    pub post: u64,
+    pub delete: u64,
+    pub other: u64,
+}
+
+impl RequestsData {
+    pub fn total(&self) -> u64 {
+        self.get + self.head + self.put + self.post + self.delete + self.other
+    }
+}
+
+xml_struct! {
+    RequestsData {
+        "Date" => date,
+        "Bucket" => bucket,
+        "Region" => region,
+        "GetRequests" => get,
+        "HeadRequests" => head,
+        "PutRequests" => put,
+        "PostRequests" => post,
+        "DeleteRequests" => delete,
+        "OtherRequests" => other,
+    }
+}
+
+#[derive(Debug, Default, Clone, RustcDecodable, RustcEncodable)]
+pub struct GetCapacityOutput {
+    pub data: Vec<CapacityData>,
+}
+
+xml_struct! {
+    GetCapacityOutput {
+        "Data" => data,
+    }
+}
+
+#[derive(Debug, Default, Clone, RustcDecodable, RustcEncodable)]
+pub struct GetTrafficOutput {
+    pub data: Vec<TrafficData>,
+}
+
+xml_struct! {
+    GetTrafficOutput {
+        "Data" => data,
+    }
+}
+
+#[derive(Debug, Default, Clone, RustcDecodable, RustcEncodable)]
+pub struct GetRequestsOutput {
+    pub data: Vec<RequestsData>,
+}
+
+xml_struct! {
+    GetRequestsOutput {
+        "Data" => data,
+    }
+}
+
+/// Usage of a bucket over a date range
+#[derive(Debug, Default, Clone, PartialEq, RustcDecodable, RustcEncodable)]
+pub struct BucketUsage {
+    pub bucket: String,
+    /// Stored bytes at the end of the range
+    pub capacity: u64,
+    pub upload: u64,
+    pub download: u64,
+    pub requests: u64,
+}
+
+/// Sum the statistics of each bucket, sorted by bucket name.
+pub fn bucket_usage(
+    capacity: &[CapacityData],
+    traffic: &[TrafficData],
+    requests: &[RequestsData],
+) -> Vec<BucketUsage> {
+    let mut usage: BTreeMap<&str, BucketUsage> = BTreeMap::new();
+    let mut latest: BTreeMap<&str, &str> = BTreeMap::new();
+
+    for data in capacity {
+        let bucket = data.bucket.as_str();
+        let date = latest.entry(bucket).or_insert("");
+        if data.date.as_str() >= *date {
+            *date = data.date.as_str();
+            usage.entry(bucket).or_insert_with(Default::default).capacity = data.capacity;
+        }
+    }
+    for data in traffic {
+        let entry = usage.entry(data.bucket.as_str()).or_insert_with(Default::default);
+        entry.upload += data.upload;
+        entry.download += data.download;
+    }
+    for data in requests {
+        let entry = usage.entry(data.bucket.as_str()).or_insert_with(Default::default);
+        entry.requests += data.total();
+    }
+
+    usage
+        .into_iter()
+        .map(|(bucket, mut usage)| {
+            usage.bucket = bucket.to_string();
+            usage
+        })
+        .collect()
+}
+
+/// Usage of a bucket during a day or an hour
+#[derive(Debug, Default, Clone, PartialEq, RustcDecodable, RustcEncodable)]
+pub struct PeriodUsage {
+    /// The `Date` of the statistics, e.g. `20171201`
+    pub date: String,
+    pub bucket: String,
+    /// Stored bytes at `date`
+    pub capacity: u64,
+    pub upload: u64,
+    pub download: u64,
+    pub requests: u64,
+}
+
+/// Merge the statistics of each bucket and period, sorted by bucket name and date.
+pub fn period_usage(
+    capacity: &[CapacityData],
+    traffic: &[TrafficData],
+    requests: &[RequestsData],
+) -> Vec<PeriodUsage> {
+    let mut usage: BTreeMap<(&str, &str), PeriodUsage> = BTreeMap::new();
+
+    for data in capacity {
+        let key = (data.bucket.as_str(), data.date.as_str());
+        usage.entry(key).or_insert_with(Default::default).capacity = data.capacity;
+    }
+    for data in traffic {
+        let key = (data.bucket.as_str(), data.date.as_str());
+        let entry = usage.entry(key).or_insert_with(Default::default);
+        entry.upload += data.upload;
+        entry.download += data.download;
+    }
+    for data in requests {
+        let key = (data.bucket.as_str(), data.date.as_str());
+        let entry = usage.entry(key).or_insert_with(Default::default);
+        entry.requests += data.total();
+    }
+
+    usage
+        .into_iter()
+        .map(|((bucket, date), mut usage)| {
+            usage.bucket = bucket.to_string();
+            usage.date = date.to_string();
+            usage
+        })
+        .collect()
+}
+
+/// A trait to the usage statistics for CTClient.
+pub trait CTClientStats {
+    /// Stored bytes.
+    fn get_capacity(&self, input: &StatsRequest) -> Result<GetCapacityOutput, CtError>;
+    /// Uploaded and downloaded bytes.
+    fn get_traffic(&self, input: &StatsRequest) -> Result<GetTrafficOutput, CtError>;
+    /// Request counts by method.
+    fn get_requests(&self, input: &StatsRequest) -> Result<GetRequestsOutput, CtError>;
+    /// Capacity, traffic and requests of each bucket, see `bucket_usage`.
+    fn get_bucket_usage(&self, input: &StatsRequest) -> Result<Vec<BucketUsage>, CtError>;
+    /// Capacity, traffic and requests of each bucket and period, see `period_usage`.
+    fn get_period_usage(&self, input: &StatsRequest) -> Result<Vec<PeriodUsage>, CtError>;
+}
+
+impl CTClientStats for CTClient {
+    fn get_capacity(&self, input: &StatsRequest) -> Result<GetCapacityOutput, CtError> {
+        try!(input.form("GetCapacity")).send_to(self, input.host(), true, &["GetCapacityResult"])
+    }
+
+    fn get_traffic(&self, input: &StatsRequest) -> Result<GetTrafficOutput, CtError> {
+        try!(input.form("GetTraffics")).send_to(self, input.host(), true, &["GetTrafficsResult"])
+    }
+
+    fn get_requests(&self, input: &StatsRequest) -> Result<GetRequestsOutput, CtError> {
+        try!(input.form("GetRequests")).send_to(self, input.host(), true, &["GetRequestsResult"])
+    }
+
+    fn get_bucket_usage(&self, input: &StatsRequest) -> Result<Vec<BucketUsage>, CtError> {
+        let capacity = try!(self.get_capacity(input));
+        let traffic = try!(self.get_traffic(input));
+        let requests = try!(self.get_requests(input));
+        Ok(bucket_usage(&capacity.data, &traffic.data, &requests.data))
+    }
+
+    fn get_period_usage(&self, input: &StatsRequest) -> Result<Vec<PeriodUsage>, CtError> {
+        let capacity = try!(self.get_capacity(input));
+        let traffic = try!(self.get_traffic(input));
+        let requests = try!(self.get_requests(input));
+        Ok(period_usage(&capacity.data, &traffic.data, &requests.data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use ct::decode::decode;
+    use super::*;
+
+    #[test]
+    fn form() {
+        let mut input = StatsRequest::new(
+            NaiveDate::from_ymd(2017, 12, 1),
+            NaiveDate::from_ymd(2017, 12, 31),
+        );
+        input.bucket = Some(String::from("photos"));
+        assert_eq!(
+            input.form("GetCapacity").unwrap().body(),
+            "Action=GetCapacity&BeginDate=20171201&EndDate=20171231&Bucket=photos&Freq=byDay"
+        );
+
+        input.begin_date = NaiveDate::from_ymd(2018, 1, 1);
+        assert!(input.form("GetCapacity").is_err());
+    }
+
+    #[test]
+    fn usage() {
+        let body = r#"<?xml version="1.0" encoding="UTF-8"?>
+<GetCapacityResponse>
+  <GetCapacityResult>
+    <Data>
+      <member><Date>20171202</Date><Bucket>a</Bucket><Capacity>300</Capacity></member>
+      <member><Date>20171201</Date><Bucket>a</Bucket><Capacity>100</Capacity></member>
+      <member><Date>20171201</Date><Bucket>b</Bucket><Capacity>50</Capacity></member>
+    </Data>
+  </GetCapacityResult>
+</GetCapacityResponse>"#;
+        let capacity: GetCapacityOutput = decode(body, &["GetCapacityResult"]).unwrap();
+        let traffic = vec![
+            TrafficData {
+                date: String::from("20171201"),
+                bucket: String::from("a"),
+                upload: 10,
+                download: 20,
+                ..Default::default()
+            },
+            TrafficData {
+                date: String::from("20171202"),
+                bucket: String::from("a"),
+                upload: 1,
+                download: 2,
+                ..Default::default()
+            },
+        ];
+        let requests = vec![
+            RequestsData {
+                bucket: String::from("b"),
+                get: 3,
+                put: 4,
+                ..Default::default()
+            },
+        ];
+
+        let usage = bucket_usage(&capacity.data, &traffic, &requests);
+        assert_eq!(usage.len(), 2);
+        assert_eq!(
+            usage[0],
+            BucketUsage {
+                bucket: String::from("a"),
+                capacity: 300,
+                upload: 11,
+                download: 22,
+                requests: 0,
+            }
+        );
+        assert_eq!(usage[1].capacity, 50);
+        assert_eq!(usage[1].requests, 7);
+
+        let periods = period_usage(&capacity.data, &traffic, &requests);
+        let rows: Vec<(&str, &str, u64, u64)> = periods
+            .iter()
+            .map(|usage| (usage.bucket.as_str(), usage.date.as_str(), usage.capacity, usage.upload))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                ("a", "20171201", 100, 10),
+                ("a", "20171202", 300, 1),
+                ("b", "", 0, 0),
+                ("b", "20171201", 50, 0),
+            ]
+        );
+        assert_eq!(periods[2].requests, 7);
+    }
+}
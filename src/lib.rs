@@ -50,6 +50,11 @@
 //!     }
 //! }
 //! ```
+//!
+//! ## Features
+//!
+//! * `async`: [`AsyncCTClient`](ct/async_client/struct.AsyncCTClient.html), the same operations
+//!   returning futures.
 
 #![crate_type = "lib"]
 extern crate aws_sdk_rust;
@@ -58,7 +63,13 @@ extern crate byteorder;
 extern crate bytes;
 extern crate chrono;
 extern crate digest;
+#[cfg(feature = "async")]
+extern crate futures;
+#[cfg(feature = "async")]
+extern crate futures_cpupool;
+extern crate httparse;
 extern crate hyper;
+extern crate hyper_openssl;
 #[macro_use]
 extern crate log;
 extern crate md_5 as md5;
@@ -72,5 +83,7 @@ extern crate xml;
 
 pub mod ct;
 
+pub use ct::error::CtError;
+
 #[cfg(test)]
 mod tests {}